@@ -2,4 +2,6 @@ use sp1_build::build_program_with_args;
 
 fn main() {
     build_program_with_args("../programs/commit", Default::default());
+    build_program_with_args("../programs/aggregate", Default::default());
+    build_program_with_args("../programs/recipe", Default::default());
 }
@@ -12,6 +12,19 @@ use alloy::{
     signers::local::PrivateKeySigner,
 };
 
+/// Address of the `PRIVATE_KEY` account and its next transaction nonce, which also serves as the
+/// nonce of the next commitment since both increase with every blob transaction.
+pub async fn committer_nonce() -> Result<(Address, u64), Box<dyn std::error::Error>> {
+    let signer: PrivateKeySigner = dotenvy::var("PRIVATE_KEY")?.parse()?;
+    let rpc_url: String = dotenvy::var("RPC_URL")?;
+    let provider = ProviderBuilder::new().connect(&rpc_url).await?;
+    let nonce = provider
+        .get_transaction_count(signer.address())
+        .pending()
+        .await?;
+    Ok((signer.address(), nonce))
+}
+
 pub async fn send_blob_tx(blob_data: &[u8]) -> Result<TxHash, Box<dyn std::error::Error>> {
     let signer: PrivateKeySigner = dotenvy::var("PRIVATE_KEY")?.parse()?;
 
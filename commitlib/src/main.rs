@@ -1,115 +1,764 @@
-use ::utils::{save_proof_as_json, ObjectJson};
-use commit_program::{CommitIn, CommitOut, ObjectOutputWithType};
-use common::ObjectOutput;
+use ::utils::{
+    load_proof_from_json_file, load_state,
+    owner::{load_owner_key, owner_key, owner_secret, sign_digest},
+    save_proof_as_json,
+    vkeys::check_vk_digest,
+    ObjectJson,
+};
+use aggregate_program::{vkeys::COMMIT_VKEY_DIGEST, AggregateIn};
+use alloy::primitives::Address;
+use clap::{Args, Parser, Subcommand};
+use commit_program::{
+    commitment_key, private_tag, Allowlist, AllowlistEntry, CommitIn, CommitOut, Holder,
+    OwnedObject, Restack, SignedSwap, Swap, Transfer, Wear,
+};
+use common::{Leaf, Object, ObjectHash, ObjectInput, ObjectOutput, OwnerKey, RecipeTable};
+use k256::ecdsa::SigningKey;
+use recipe_program::{Derivation, RecipeIn};
 use sha2::{Digest, Sha256};
 use sp1_sdk::{
     include_elf, utils, EnvProver, HashableKey, ProverClient, SP1Proof, SP1ProofWithPublicValues,
     SP1Stdin,
 };
 
-use crate::eth::send_blob_tx;
+use crate::eth::{committer_nonce, send_blob_tx};
 
 const COMMIT_ELF: &[u8] = include_elf!("commit-program");
+const AGGREGATE_ELF: &[u8] = include_elf!("aggregate-program");
+const RECIPE_ELF: &[u8] = include_elf!("recipe-program");
 
 mod eth;
 
-fn commit_objects(
-    client: &EnvProver,
-    object_jsons: Vec<ObjectJson>,
-    commit_pk: &sp1_sdk::SP1ProvingKey,
-    commit_vk: &sp1_sdk::SP1VerifyingKey,
-) -> (CommitOut, SP1ProofWithPublicValues) {
+/// Objects, restacks, transfers, swaps and burns committed together.
+#[derive(Default)]
+struct Batch {
+    objects: Vec<ObjectJson>,
+    /// Sources of each restack, with the pieces they are restacked into.
+    restacks: Vec<(Vec<ObjectJson>, Vec<Object>)>,
+    /// Tools used by the objects, with their successors, or `None` for the tools that wear out.
+    wears: Vec<(ObjectJson, Option<Object>)>,
+    transfers: Vec<Transfer>,
+    /// Swaps signed by all their owners.
+    swaps: Vec<SignedSwap>,
+    burns: Vec<ObjectHash>,
+}
+
+/// Writes the inputs of the commit program for `batch`, whose objects are proved by programs of
+/// `allowlist`. The batch is bound to `committer`'s aggregate if it has no `nonce`.
+fn commit_stdin(
+    (committer, nonce): (Address, Option<u64>),
+    allowlist: &Allowlist,
+    batch: Batch,
+    private: bool,
+    signing_key: &SigningKey,
+) -> SP1Stdin {
     let mut commit_stdin = SP1Stdin::new();
 
-    let mut objects: Vec<ObjectOutputWithType> = Vec::new();
-    for obj_json in &object_jsons {
-        let object_output: ObjectOutput = obj_json.proof.public_values.clone().read();
+    let objects: Vec<ObjectOutput> = batch.objects.iter().map(ObjectJson::output).collect();
+    let restacks: Vec<Restack> = batch
+        .restacks
+        .iter()
+        .map(|(sources, pieces)| Restack {
+            sources: sources.iter().map(ObjectJson::output).collect(),
+            pieces: pieces.clone(),
+        })
+        .collect();
+
+    // All consumed, referenced, worn, transferred and burned objects are expected to belong to the
+    // committer.
+    // Consumed objects are private if the state holds their commitment rather than the object.
+    let owner = owner_key(signing_key);
+    let secret = owner_secret(signing_key);
+    let wears: Vec<Wear> = batch
+        .wears
+        .iter()
+        .map(|(tool, successor)| Wear {
+            tool: tool.output(),
+            owner,
+            successor: successor.clone(),
+        })
+        .collect();
+    // The batch's state updates are proved against the state last published by the synchronizer.
+    let mut state = load_state().expect("failed to load state");
+    let created_tags = objects
+        .iter()
+        .map(|o| o.hash)
+        .chain(
+            restacks
+                .iter()
+                .flat_map(|r| r.pieces.iter().map(Object::hash)),
+        )
+        .map(|hash| private.then(|| private_tag(&hash, &secret)))
+        .collect();
+    let consumed_holders = objects
+        .iter()
+        .flat_map(|o| o.consumed.iter().copied())
+        .chain(
+            restacks
+                .iter()
+                .flat_map(|r| r.sources.iter().map(|s| s.hash)),
+        )
+        .map(|hash| match state.get(&commitment_key(&hash).into()) {
+            Leaf::Committed(_) => Holder::Secret(secret),
+            _ => Holder::Owner(owner),
+        })
+        .collect();
+    let num_referenced = objects.iter().map(|o| o.referenced.len()).sum();
+    let mut commit_input = CommitIn {
+        committer: committer.into(),
+        nonce: nonce.unwrap_or_default(),
+        aggregated: nonce.is_none(),
+        prev_root: state.root(),
+        allowlist: allowlist.root(),
+        objects,
+        restacks,
+        wears,
+        allowlist_proofs: vec![],
+        created_tags,
+        consumed_holders,
+        referenced_owners: vec![owner; num_referenced],
+        transfers: batch.transfers,
+        swaps: batch.swaps,
+        burns: batch
+            .burns
+            .iter()
+            .map(|&hash| OwnedObject { hash, owner })
+            .collect(),
+        witnesses: vec![],
+        signatures: vec![],
+    };
+    commit_input.allowlist_proofs = commit_input
+        .verified_objects()
+        .map(|o| {
+            allowlist
+                .prove(&allowlist_entry(o))
+                .expect("object is allowed")
+        })
+        .collect();
+    commit_input.witnesses = state.apply(&commit_input.state_updates());
+    let commit_out = commit_input.out();
+    assert!(
+        commit_out
+            .owners()
+            .into_iter()
+            .chain(commit_input.private_owners())
+            .all(|o| o == owner),
+        "all committed objects must be owned by {}",
+        owner
+    );
+    let commit_digest = commit_out.digest();
+    commit_input
+        .signatures
+        .push((owner, sign_digest(signing_key, &commit_digest)));
+    commit_stdin.write(&commit_input);
 
-        objects.push(ObjectOutputWithType {
-            hash: object_output.hash.clone(),
-            consumed: object_output.consumed.clone(),
-            blueprint: obj_json.object.blueprint.clone(),
-        });
+    // In the order of `CommitIn::verified_objects`.
+    let sources = batch.restacks.iter().flat_map(|(sources, _)| sources);
+    let tools = batch.wears.iter().map(|(tool, _)| tool);
+    for obj_json in batch.objects.iter().chain(sources).chain(tools) {
+        obj_json.write_to(&mut commit_stdin);
     }
 
-    let commit_input = CommitIn { objects: objects };
-    commit_stdin.write(&commit_input);
+    commit_stdin
+}
+
+/// Pieces of the given quantities restacked from `sources`, owned by `owner`.
+fn restack_pieces(sources: &[ObjectJson], quantities: &[u32], owner: OwnerKey) -> Vec<Object> {
+    let blueprint = sources
+        .first()
+        .expect("restack some objects")
+        .object
+        .blueprint;
+    quantities
+        .iter()
+        .map(|&quantity| Object {
+            key: rand::random(),
+            owner,
+            inputs: sources.iter().map(|source| source.hash).collect(),
+            catalysts: vec![],
+            seed: 0,
+            blueprint,
+            quantity,
+            durability: None,
+            anchor: None,
+        })
+        .collect()
+}
 
-    for obj_json in object_jsons {
-        let object_output: ObjectOutput = obj_json.proof.public_values.clone().read();
-        commit_stdin.write(&object_output);
+/// Successor of `tool` once worn by the uses of `objects`, owned by `owner`, or `None` if the tool
+/// wears out.
+fn tool_successor(tool: &ObjectJson, objects: &[ObjectJson], owner: OwnerKey) -> Option<Object> {
+    let uses: u64 = objects
+        .iter()
+        .map(ObjectJson::output)
+        .filter(|object| object.worn.contains(&tool.hash))
+        .map(|object| object.quantity as u64)
+        .sum();
+    assert!(uses > 0, "tool {} is not used by the objects", tool.hash);
+    let durability = tool.object.durability.expect("object is not a tool");
+    let left = (durability as u64)
+        .checked_sub(uses)
+        .unwrap_or_else(|| panic!("tool {} has only {} uses left", tool.hash, durability));
+    (left > 0).then(|| Object {
+        key: rand::random(),
+        owner,
+        inputs: vec![tool.hash],
+        catalysts: vec![],
+        seed: 0,
+        blueprint: tool.object.blueprint,
+        quantity: 1,
+        durability: Some(left as u32),
+        anchor: None,
+    })
+}
 
-        let SP1Proof::Compressed(obj_compressed) = obj_json.proof.proof else {
-            panic!("expected compressed proof")
+/// Proves `object`, restacked or worn from `inputs` by the commit program, with the recipe
+/// program, so that it can be used like any other object once the batch is committed.
+fn prove_derived(
+    (client, cycles): (&EnvProver, bool),
+    (pk, vk): (&sp1_sdk::SP1ProvingKey, &sp1_sdk::SP1VerifyingKey),
+    (object, derivation): (Object, Derivation),
+    inputs: &[ObjectJson],
+) -> ObjectJson {
+    let name = match derivation {
+        Derivation::Craft(_) => "Craft",
+        Derivation::Restack => "Restack",
+        Derivation::Wear => "Wear",
+    };
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&RecipeIn {
+        object: ObjectInput {
+            object: object.clone(),
+            work: [0u8; 32],
+        },
+        derivation,
+        recipe_table: RecipeTable::current().ids(),
+        program: vk.hash_u32(),
+    });
+    for input in inputs {
+        input.write_to(&mut stdin);
+    }
+    let proof = prove((client, cycles), name, RECIPE_ELF, (pk, vk), &stdin, false);
+    ObjectJson {
+        hash: object.hash(),
+        object,
+        work: [0u8; 32],
+        proof,
+        program_vk: vk.clone(),
+    }
+}
+
+/// Writes the inputs of the aggregate program for the compressed commit proofs at `paths`.
+fn aggregate_stdin(
+    (committer, nonce): (Address, u64),
+    paths: &[String],
+    commit_vk: &sp1_sdk::SP1VerifyingKey,
+) -> SP1Stdin {
+    let proofs: Vec<SP1ProofWithPublicValues> = paths
+        .iter()
+        .map(|path| load_proof_from_json_file(path).expect("failed to load commit proof"))
+        .collect();
+    let batches: Vec<CommitOut> = proofs
+        .iter()
+        .map(|proof| proof.public_values.clone().read())
+        .collect();
+
+    // The merged batch is proved against the state last published by the synchronizer, which
+    // every batch must also have been proved against.
+    let mut state = load_state().expect("failed to load state");
+    let merged = CommitOut::merge(committer.into(), nonce, &batches);
+    assert!(
+        merged.prev_root == state.root(),
+        "batches apply to an outdated state root"
+    );
+    let aggregate_input = AggregateIn {
+        committer: committer.into(),
+        nonce,
+        witnesses: state.apply(&merged.state_updates()),
+        batches,
+    };
+
+    let mut aggregate_stdin = SP1Stdin::new();
+    aggregate_stdin.write(&aggregate_input);
+    for proof in proofs {
+        let SP1Proof::Compressed(compressed) = proof.proof else {
+            panic!("expected compressed commit proof")
         };
-        commit_stdin.write_proof(*obj_compressed, obj_json.program_vk.clone().vk);
+        aggregate_stdin.write_proof(*compressed, commit_vk.vk.clone());
+    }
+    aggregate_stdin
+}
+
+/// Proves `stdin` with the program `elf`, as a compressed proof to be aggregated or as a Groth16
+/// proof to be sent on chain. With `cycles`, the program is first executed to print its cycle
+/// count.
+fn prove(
+    (client, cycles): (&EnvProver, bool),
+    name: &str,
+    elf: &[u8],
+    (pk, vk): (&sp1_sdk::SP1ProvingKey, &sp1_sdk::SP1VerifyingKey),
+    stdin: &SP1Stdin,
+    groth16: bool,
+) -> SP1ProofWithPublicValues {
+    if cycles {
+        let (_, report) = client
+            .execute(elf, stdin)
+            .run()
+            .unwrap_or_else(|e| panic!("{} execution failed: {}", name, e));
+        println!("{} cycles: {}", name, report.total_instruction_count());
     }
 
     let start = std::time::Instant::now();
-    let mut commit_proof: SP1ProofWithPublicValues = client
-        .prove(commit_pk, &commit_stdin)
-        .groth16()
+    let prover = client.prove(pk, stdin);
+    let prover = if groth16 {
+        prover.groth16()
+    } else {
+        prover.compressed()
+    };
+    let proof = prover
         .run()
-        .expect("commit proving failed");
+        .unwrap_or_else(|e| panic!("{} proving failed: {}", name, e));
     let duration = start.elapsed();
-    println!("\nTotal commit proof creation time: {:?}", duration);
+    println!("\nTotal {} proof creation time: {:?}", name, duration);
 
     client
-        .verify(&commit_proof, commit_vk)
-        .expect("commit verify failed");
+        .verify(&proof, vk)
+        .unwrap_or_else(|e| panic!("{} verify failed: {}", name, e));
+    proof
+}
+
+/// Saves a proof under its hash, and returns the hash.
+fn save_proof(proof: &SP1ProofWithPublicValues, dir: &str) -> [u8; 32] {
+    let proof_hash: [u8; 32] =
+        Sha256::digest(&bincode::serialize(proof).expect("Failed to serialize proof")).into();
+    save_proof_as_json(proof, format!("{}/{}.json", dir, hex::encode(proof_hash)))
+        .expect("failed to save proof");
+    proof_hash
+}
 
-    let committed_output: CommitOut = commit_proof.public_values.read();
+/// Entry of the allowlist that must contain the program and recipe table of `object`.
+fn allowlist_entry(object: &ObjectOutput) -> AllowlistEntry {
+    AllowlistEntry {
+        program: object.program,
+        recipe_table: object.recipe_table,
+    }
+}
+
+/// Writes a swap offer, to be countersigned by the other owners of the swap.
+fn save_offer(offer: &SignedSwap, path: &str) {
+    let json = serde_json::to_string_pretty(offer).expect("failed to serialize offer");
+    std::fs::write(path, json).expect("failed to save offer");
+}
 
-    (committed_output, commit_proof)
+fn load_offer(path: &str) -> SignedSwap {
+    let json = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("failed to load offer {}: {}", path, e));
+    serde_json::from_str(&json).unwrap_or_else(|e| panic!("invalid offer {}: {}", path, e))
+}
+
+fn load_object(path: &str, allowlist: &Allowlist) -> ObjectJson {
+    match ObjectJson::from_json_file(path) {
+        Ok(obj_json) => {
+            println!("Loaded object from {}", path);
+            let output = obj_json.output();
+            if let Err(e) = check_vk_digest("object", &obj_json.program_vk, &output.program) {
+                eprintln!("Object {} can't be committed: {}", path, e);
+                std::process::exit(1);
+            }
+            if allowlist.prove(&allowlist_entry(&output)).is_none() {
+                eprintln!(
+                    "Object {} can't be committed: its program or recipe table is not allowed",
+                    path
+                );
+                std::process::exit(1);
+            }
+            obj_json
+        }
+        Err(e) => {
+            eprintln!("Failed to load {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[derive(Parser)]
+#[command(
+    about = "Commits digital objects",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    /// Executes each program once before proving it, and prints its cycle count.
+    #[arg(long, global = true)]
+    cycles: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    commit: CommitArgs,
+}
+
+/// Objects committed when no subcommand is given.
+#[derive(Args)]
+struct CommitArgs {
+    /// Publishes the objects as commitments instead of their hashes and owners.
+    #[arg(long)]
+    private: bool,
+    /// Proves a batch bound to this aggregator's address, saved in `batches/` instead of sent.
+    #[arg(long = "for", value_name = "AGGREGATOR")]
+    aggregator: Option<Address>,
+    /// Tool used as a catalyst by the objects, which they wear.
+    #[arg(long = "tool", value_name = "TOOL")]
+    tools: Vec<String>,
+    #[arg(value_name = "OBJECT", required = true)]
+    objects: Vec<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Transfers an object to a new owner.
+    Transfer {
+        object: String,
+        #[arg(value_name = "NEW_OWNER_KEY")]
+        to: OwnerKey,
+    },
+    /// Burns objects without crafting anything.
+    Burn {
+        #[arg(value_name = "OBJECT", required = true)]
+        objects: Vec<String>,
+    },
+    /// Offers objects to the counterparty for theirs, saved in `offers/` to be countersigned.
+    Offer {
+        #[arg(value_name = "COUNTERPARTY_KEY")]
+        counterparty: OwnerKey,
+        /// Objects given to the counterparty.
+        #[arg(value_name = "GIVE", required = true)]
+        gives: Vec<String>,
+        /// Hashes of the counterparty's objects taken in exchange.
+        #[arg(long = "take", value_name = "HASH", num_args = 1.., required = true)]
+        takes: Vec<ObjectHash>,
+    },
+    /// Countersigns an offer, and commits it once every owner signed it.
+    Accept { offer: String },
+    /// Splits a stack into pieces of the given quantities.
+    Split {
+        stack: String,
+        #[arg(value_name = "QUANTITY", num_args = 2.., required = true)]
+        quantities: Vec<u32>,
+    },
+    /// Merges stacks of the same blueprint into one.
+    Merge {
+        #[arg(value_name = "STACK", num_args = 2.., required = true)]
+        stacks: Vec<String>,
+    },
+    /// Merges batches proved for this address into a single commitment.
+    Aggregate {
+        #[arg(value_name = "BATCH", required = true)]
+        batches: Vec<String>,
+    },
 }
 
 #[tokio::main]
 async fn main() {
     utils::setup_logger();
 
+    let cli = Cli::parse();
+
     let client = ProverClient::from_env();
 
     std::fs::create_dir_all("commitments").expect("failed to create commitments directory");
+    std::fs::create_dir_all("batches").expect("failed to create batches directory");
 
     println!("Setting up proving/verifying keys...");
     let (commit_pk, commit_vk) = client.setup(COMMIT_ELF);
     println!("commit program vk {}", hex::encode(commit_vk.hash_bytes()));
+    // Commit proofs are verified by the aggregate program, so the commit vk must be the one that
+    // program was built with.
+    check_vk_digest("commit", &commit_vk, &COMMIT_VKEY_DIGEST).expect("stale vkey");
+    let (recipe_pk, recipe_vk) = client.setup(RECIPE_ELF);
+    let allowlist = Allowlist::current(recipe_vk.hash_u32());
+    println!("allowlist root {}", hex::encode(allowlist.root()));
 
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <object1.json> [object2.json] ...", args[0]);
-        std::process::exit(1);
-    }
+    let commit_proof = if let Some(Command::Aggregate { batches }) = &cli.command {
+        let (committer, nonce) = committer_nonce()
+            .await
+            .expect("failed to get committer nonce");
+        let (aggregate_pk, aggregate_vk) = client.setup(AGGREGATE_ELF);
+        println!(
+            "aggregate program vk {}",
+            hex::encode(aggregate_vk.hash_bytes())
+        );
+        println!(
+            "Aggregating {} batches as {} with nonce {}",
+            batches.len(),
+            committer,
+            nonce
+        );
+        let stdin = aggregate_stdin((committer, nonce), batches, &commit_vk);
+        prove(
+            (&client, cli.cycles),
+            "Aggregate",
+            AGGREGATE_ELF,
+            (&aggregate_pk, &aggregate_vk),
+            &stdin,
+            true,
+        )
+    } else {
+        let signing_key = load_owner_key().expect("failed to load owner key");
+        let (batch, private, aggregator) = match cli.command {
+            Some(Command::Aggregate { .. }) => unreachable!("aggregate is handled above"),
+            Some(Command::Transfer { object, to }) => {
+                let object = load_object(&object, &allowlist);
+                let transfer = Transfer {
+                    hash: object.hash,
+                    from: owner_key(&signing_key),
+                    to,
+                };
+                println!("Transferring {} to {}", transfer.hash, transfer.to);
+                let batch = Batch {
+                    transfers: vec![transfer],
+                    ..Default::default()
+                };
+                (batch, false, None)
+            }
+            Some(Command::Offer {
+                counterparty,
+                gives,
+                takes,
+            }) => {
+                // An offer gives our objects to the counterparty for theirs, and is only valid
+                // once countersigned by them.
+                let owner = owner_key(&signing_key);
+                let gives = gives.iter().map(|path| Transfer {
+                    hash: load_object(path, &allowlist).hash,
+                    from: owner,
+                    to: counterparty,
+                });
+                let takes = takes.iter().map(|&hash| Transfer {
+                    hash,
+                    from: counterparty,
+                    to: owner,
+                });
+                let swap = Swap {
+                    salt: rand::random(),
+                    legs: gives.chain(takes).collect(),
+                };
+                swap.check();
+                let digest = swap.digest();
+                let offer = SignedSwap {
+                    signatures: vec![(owner, sign_digest(&signing_key, &digest))],
+                    swap,
+                };
+                std::fs::create_dir_all("offers").expect("failed to create offers directory");
+                let path = format!("offers/{}.json", hex::encode(digest));
+                save_offer(&offer, &path);
+                println!("\n✓ Offer saved to {}, send it to {}!", path, counterparty);
+                return;
+            }
+            Some(Command::Accept { offer: path }) => {
+                let mut offer = load_offer(&path);
+                offer.swap.check();
+                let owner = owner_key(&signing_key);
+                if !offer.swap.parties().contains(&owner) {
+                    eprintln!("Offer {} is not for {}", path, owner);
+                    std::process::exit(1);
+                }
+                for leg in &offer.swap.legs {
+                    println!("Swapping {} from {} to {}", leg.hash, leg.from, leg.to);
+                }
+                if offer.missing_signatures().contains(&owner) {
+                    let signature = sign_digest(&signing_key, &offer.swap.digest());
+                    offer.signatures.push((owner, signature));
+                }
+                // Swaps between more than two owners go around until every owner signed.
+                let missing = offer.missing_signatures();
+                if !missing.is_empty() {
+                    save_offer(&offer, &path);
+                    for owner in missing {
+                        println!("Offer {} still needs the signature of {}", path, owner);
+                    }
+                    return;
+                }
+                let batch = Batch {
+                    swaps: vec![offer],
+                    ..Default::default()
+                };
+                (batch, false, None)
+            }
+            Some(Command::Burn { objects }) => {
+                let burns: Vec<ObjectHash> = objects
+                    .iter()
+                    .map(|path| load_object(path, &allowlist).hash)
+                    .collect();
+                for hash in &burns {
+                    println!("Burning {}", hash);
+                }
+                let batch = Batch {
+                    burns,
+                    ..Default::default()
+                };
+                (batch, false, None)
+            }
+            Some(command @ (Command::Split { .. } | Command::Merge { .. })) => {
+                // A split restacks one stack into pieces of the given quantities, and a merge
+                // restacks several stacks into one.
+                let (sources, quantities): (Vec<ObjectJson>, Vec<u32>) = match command {
+                    Command::Split { stack, quantities } => {
+                        (vec![load_object(&stack, &allowlist)], quantities)
+                    }
+                    Command::Merge { stacks } => {
+                        let sources: Vec<ObjectJson> = stacks
+                            .iter()
+                            .map(|path| load_object(path, &allowlist))
+                            .collect();
+                        let total = sources
+                            .iter()
+                            .try_fold(0u32, |total, s| total.checked_add(s.object.quantity))
+                            .expect("merged quantity overflows");
+                        (sources, vec![total])
+                    }
+                    _ => unreachable!("split or merge"),
+                };
+                let pieces = restack_pieces(&sources, &quantities, owner_key(&signing_key));
+                std::fs::create_dir_all("objects").expect("failed to create objects directory");
+                for piece in &pieces {
+                    let piece_json = prove_derived(
+                        (&client, cli.cycles),
+                        (&recipe_pk, &recipe_vk),
+                        (piece.clone(), Derivation::Restack),
+                        &sources,
+                    );
+                    let filename = format!("objects/{}_{}.json", piece.blueprint, piece_json.hash);
+                    piece_json
+                        .save_as_json(&filename)
+                        .expect("failed to save restacked object");
+                    println!(
+                        "Restacked {} {} saved to {}",
+                        piece.quantity, piece.blueprint, filename
+                    );
+                }
+                let batch = Batch {
+                    restacks: vec![(sources, pieces)],
+                    ..Default::default()
+                };
+                (batch, false, None)
+            }
+            None => {
+                // Private objects are published as commitments, and consumed without revealing
+                // them. Batches for an aggregator are bound to its address instead of ours.
+                let CommitArgs {
+                    private,
+                    aggregator,
+                    tools,
+                    objects,
+                } = cli.commit;
+                // Tools used as catalysts are worn by the objects, and must be passed along.
+                let tools: Vec<ObjectJson> = tools
+                    .iter()
+                    .map(|path| load_object(path, &allowlist))
+                    .collect();
+                let objects: Vec<ObjectJson> = objects
+                    .iter()
+                    .map(|path| load_object(path, &allowlist))
+                    .collect();
+                for object in &objects {
+                    for hash in &object.output().worn {
+                        if !tools.iter().any(|tool| tool.hash == *hash) {
+                            eprintln!(
+                                "Object {} uses tool {}, pass it with --tool",
+                                object.hash, hash
+                            );
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                let owner = owner_key(&signing_key);
+                let wears = tools
+                    .into_iter()
+                    .map(|tool| {
+                        let successor = tool_successor(&tool, &objects, owner);
+                        match &successor {
+                            Some(successor) => {
+                                let successor_json = prove_derived(
+                                    (&client, cli.cycles),
+                                    (&recipe_pk, &recipe_vk),
+                                    (successor.clone(), Derivation::Wear),
+                                    std::slice::from_ref(&tool),
+                                );
+                                std::fs::create_dir_all("objects")
+                                    .expect("failed to create objects directory");
+                                let filename = format!(
+                                    "objects/{}_{}.json",
+                                    successor.blueprint, successor_json.hash
+                                );
+                                successor_json
+                                    .save_as_json(&filename)
+                                    .expect("failed to save worn tool");
+                                println!(
+                                    "Worn {} with {} uses left saved to {}",
+                                    successor.blueprint,
+                                    successor.durability.expect("tool has durability"),
+                                    filename
+                                );
+                            }
+                            None => println!("Tool {} wears out", tool.hash),
+                        }
+                        (tool, successor)
+                    })
+                    .collect();
+                let batch = Batch {
+                    objects,
+                    wears,
+                    ..Default::default()
+                };
+                (batch, private, aggregator)
+            }
+        };
 
-    let mut objects: Vec<ObjectJson> = Vec::new();
-    for path in &args[1..] {
-        match ObjectJson::from_json_file(path) {
-            Ok(obj_json) => {
-                println!("Loaded object from {}", path);
-                objects.push(obj_json);
+        let stdin = match aggregator {
+            Some(aggregator) => {
+                println!("Committing a batch for aggregator {}", aggregator);
+                // The batch is only valid as part of the aggregate, with the aggregator's nonce.
+                commit_stdin((aggregator, None), &allowlist, batch, private, &signing_key)
             }
-            Err(e) => {
-                eprintln!("Failed to load {}: {}", path, e);
-                std::process::exit(1);
+            None => {
+                let (committer, nonce) = committer_nonce()
+                    .await
+                    .expect("failed to get committer nonce");
+                println!("Committing as {} with nonce {}", committer, nonce);
+                commit_stdin(
+                    (committer, Some(nonce)),
+                    &allowlist,
+                    batch,
+                    private,
+                    &signing_key,
+                )
             }
+        };
+        let commit_proof = prove(
+            (&client, cli.cycles),
+            "Commit",
+            COMMIT_ELF,
+            (&commit_pk, &commit_vk),
+            &stdin,
+            aggregator.is_none(),
+        );
+        if aggregator.is_some() {
+            let batch_hash = save_proof(&commit_proof, "batches");
+            println!(
+                "\n✓ Batch saved to batches/{}.json, send it to the aggregator!",
+                hex::encode(batch_hash)
+            );
+            return;
         }
-    }
+        commit_proof
+    };
 
-    let (committed_output, commit_proof) = commit_objects(&client, objects, &commit_pk, &commit_vk);
+    let committed_output: CommitOut = commit_proof.public_values.clone().read();
     println!("Committed output: {:?}", committed_output);
 
-    let commit_proof_hash: [u8; 32] = Sha256::digest(
-        &bincode::serialize(&commit_proof).expect("Failed to serialize commit proof"),
-    )
-    .into();
+    let commit_proof_hash = save_proof(&commit_proof, "commitments");
     println!("Commit proof hash: {}", hex::encode(commit_proof_hash));
 
-    save_proof_as_json(
-        &commit_proof,
-        &format!("commitments/{}.json", hex::encode(commit_proof_hash)),
-    )
-    .expect("failed to save commit proof");
-
     // Note: We cannot send the full commit proof as blob data due to size limits.
     // let commitment_blob_data: Vec<u8> =
     //     bincode::serialize(&commit_proof).expect("failed to serialize commit proof");
@@ -1,38 +1,143 @@
-use std::vec;
+use std::path::{Path, PathBuf};
 
+use clap::{Arg, ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand};
 use sp1_sdk::{
     include_elf, utils, EnvProver, HashableKey, ProverClient, SP1Proof, SP1ProofWithPublicValues,
     SP1Stdin,
 };
 
-use ::utils::ObjectJson;
-use axe_program::constants::{AXE_BLUEPRINT, AXE_MINING_MAX};
-use common::{difficulty, Object, ObjectInput, ObjectOutput};
+use ::utils::{
+    owner::{load_owner_key, owner_key},
+    vkeys::check_vk_digest,
+    ObjectJson,
+};
+use common::{
+    Blueprint, Object, ObjectHash, ObjectInput, ObjectOutput, OwnerKey, Rarity, Recipe,
+    RecipeTable, Work,
+};
 use pow_program::{PowIn, PowOut};
-use stone_program::constants::{STONE_BLUEPRINT, STONE_MINING_MAX};
-use wood_program::constants::{WOOD_BLUEPRINT, WOOD_MINING_MAX};
+use recipe_program::{vkeys::POW_VKEY_DIGEST, Derivation, RecipeIn};
 
 const POW_ELF: &[u8] = include_elf!("pow-program");
-const STONE_ELF: &[u8] = include_elf!("stone-program");
-const WOOD_ELF: &[u8] = include_elf!("wood-program");
-const AXE_ELF: &[u8] = include_elf!("axe-program");
-
-fn mine_object(blueprint: &str, max_difficulty: u64, inputs: Vec<String>) -> (Object, String) {
-    let key = {
-        let bytes: [u8; 32] = rand::random();
-        hex::encode(bytes)
+const RECIPE_ELF: &[u8] = include_elf!("recipe-program");
+
+#[derive(Parser)]
+#[command(about = "Mines and crafts digital objects")]
+struct Cli {
+    /// Directory the objects are saved in.
+    #[arg(long, global = true, default_value = "objects")]
+    out_dir: PathBuf,
+    /// Lowest rarity tier of the objects mined.
+    #[arg(long, global = true, default_value = "common")]
+    rarity: Rarity,
+    /// Recent beacon block root the objects are mined after, so that they can't have been mined
+    /// in advance.
+    #[arg(long, global = true, value_parser = parse_block_root)]
+    anchor: Option<[u8; 32]>,
+    /// Executes each program once before proving it, and prints its cycle count.
+    #[arg(long, global = true)]
+    cycles: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Mines objects of a blueprint crafted from nothing, like wood or stone.
+    Mine {
+        blueprint: Blueprint,
+        /// Number of objects mined.
+        #[arg(long, default_value_t = 1)]
+        count: u32,
+        /// Quantity of each object, mined as a single stack.
+        #[arg(long, default_value_t = 1)]
+        quantity: u32,
+    },
+    /// Crafts an object from existing objects, passed by blueprint, like
+    /// `craft axe --wood objects/wood_1.json --stone objects/stone_1.json`.
+    Craft {
+        blueprint: Blueprint,
+        /// Quantity of the object, crafted as a single stack.
+        #[arg(long, default_value_t = 1)]
+        quantity: u32,
+    },
+    /// Crafts 2 woods, 1 stone, 1 axe made up of a wood and the stone, and 1 plank cut from the
+    /// other wood with the axe.
+    Demo,
+}
+
+fn parse_block_root(s: &str) -> Result<[u8; 32], hex::FromHexError> {
+    let mut root = [0u8; 32];
+    hex::decode_to_slice(s.trim_start_matches("0x"), &mut root)?;
+    Ok(root)
+}
+
+/// First name `{prefix}_{i}`, counting from 1, that no object saved in `dir` has.
+fn next_name(dir: &Path, prefix: &str) -> String {
+    (1..)
+        .map(|i| format!("{}_{}", prefix, i))
+        .find(|name| !dir.join(format!("{}.json", name)).exists())
+        .expect("free object name")
+}
+
+fn load_object(path: &str) -> ObjectJson {
+    ObjectJson::from_json_file(path).unwrap_or_else(|e| {
+        eprintln!("Failed to load {}: {}", path, e);
+        std::process::exit(1);
+    })
+}
+
+/// Measures how many hashes per second this machine computes for objects like `template`.
+fn measure_hash_rate(template: &Object) -> f64 {
+    const SAMPLES: u32 = 10_000;
+    let mut obj = template.clone();
+    let start = std::time::Instant::now();
+    for seed in 0..SAMPLES {
+        obj.seed = seed;
+        std::hint::black_box(obj.hash());
+    }
+    SAMPLES as f64 / start.elapsed().as_secs_f64()
+}
+
+/// Mines a stack of `quantity` objects of `recipe`, which takes as long as mining them one by one,
+/// of at least the tier `rarity`, after the beacon block `anchor` if any.
+fn mine_object(
+    recipe: &Recipe,
+    (quantity, rarity): (u32, Rarity),
+    (owner, anchor): (OwnerKey, Option<[u8; 32]>),
+    inputs: Vec<ObjectHash>,
+    catalysts: Vec<ObjectHash>,
+) -> (Object, ObjectHash) {
+    let blueprint = recipe.blueprint;
+    let target = rarity.target(&recipe.target.for_quantity(quantity));
+    let key: [u8; 32] = rand::random();
+    let mut obj = Object {
+        key,
+        owner,
+        inputs,
+        catalysts,
+        seed: 0,
+        blueprint,
+        quantity,
+        durability: recipe.durability,
+        anchor,
     };
 
-    for seed in 0u32..=u32::MAX {
-        let obj = Object {
-            key: key.clone(),
-            inputs: inputs.clone(),
-            seed,
-            blueprint: blueprint.to_string(),
-        };
+    let hash_rate = measure_hash_rate(&obj);
+    println!(
+        "Mining {} {} with target {:08x}: expecting {:.0} hashes, ~{:.2?} at {:.0} hashes/s",
+        rarity,
+        blueprint,
+        target.to_compact(),
+        target.expected_hashes(),
+        target.expected_time(hash_rate),
+        hash_rate
+    );
 
+    for seed in 0u32..=u32::MAX {
+        obj.seed = seed;
         let h = obj.hash();
-        if difficulty(&h) <= max_difficulty {
+        if target.is_met_by(&h) {
             return (obj, h);
         }
     }
@@ -40,15 +145,20 @@ fn mine_object(blueprint: &str, max_difficulty: u64, inputs: Vec<String>) -> (Ob
     panic!("failed to mine {} object", blueprint);
 }
 
+fn report_cycles(client: &EnvProver, name: &str, elf: &[u8], stdin: &SP1Stdin) {
+    let (_, report) = client.execute(elf, stdin).run().expect("execution failed");
+    println!("{} cycles: {}", name, report.total_instruction_count());
+}
+
 fn create_pow_proof(
     client: &EnvProver,
     pow_pk: &sp1_sdk::SP1ProvingKey,
     pow_vk: &sp1_sdk::SP1VerifyingKey,
-    n_iters: u32,
-    input: String,
+    work: Work,
+    input: ObjectHash,
 ) -> (PowOut, SP1Proof) {
     let mut pow_stdin = SP1Stdin::new();
-    pow_stdin.write(&PowIn { n_iters, input });
+    pow_stdin.write(&PowIn::new(work, input.0));
 
     let pow_proof: SP1ProofWithPublicValues = client
         .prove(pow_pk, &pow_stdin)
@@ -69,236 +179,238 @@ fn create_pow_proof(
     (pow_out, SP1Proof::Compressed(compressed_proof))
 }
 
-fn create_stone_object(
-    client: &EnvProver,
-    pow_pk: &sp1_sdk::SP1ProvingKey,
-    pow_vk: &sp1_sdk::SP1VerifyingKey,
-    stone_pk: &sp1_sdk::SP1ProvingKey,
-    stone_vk: &sp1_sdk::SP1VerifyingKey,
-) -> ObjectJson {
-    let (obj, obj_hash) = mine_object(STONE_BLUEPRINT, STONE_MINING_MAX, vec![]);
-    println!("Mined stone: seed={}, hash={}", obj.seed, obj_hash);
-
-    println!("Creating POW proof for stone...");
-    let (pow_out, pow_proof) = create_pow_proof(client, pow_pk, pow_vk, 3, obj_hash);
-    println!("Completed POW proof for stone, {}.", pow_out.output.clone());
-
-    let mut stone_stdin = SP1Stdin::new();
-    stone_stdin.write(&ObjectInput {
-        object: obj.clone(),
-        work: pow_out.output.clone(),
-    });
-    stone_stdin.write(&pow_out);
-
-    let SP1Proof::Compressed(compressed_proof) = pow_proof else {
-        panic!("expected compressed proof")
-    };
-    stone_stdin.write_proof(*compressed_proof, pow_vk.clone().vk);
-
-    let start = std::time::Instant::now();
-    let stone_proof: SP1ProofWithPublicValues = client
-        .prove(stone_pk, &stone_stdin)
-        .compressed()
-        .run()
-        .expect("stone proving failed");
-    let duration = start.elapsed();
-    println!("Stone proving time: {:?}", duration);
-
-    client
-        .verify(&stone_proof, stone_vk)
-        .expect("stone verify failed");
-
-    let committed_output: ObjectOutput = stone_proof.public_values.clone().read();
-    println!("Stone committed hash: {}", committed_output.hash);
-
-    ObjectJson {
-        object: obj,
-        hash: committed_output.hash,
-        work: pow_out.output.clone(),
-        proof: stone_proof,
-        program_vk: stone_vk.clone(),
-    }
-}
-
-fn create_wood_object(
-    client: &EnvProver,
-    wood_pk: &sp1_sdk::SP1ProvingKey,
-    wood_vk: &sp1_sdk::SP1VerifyingKey,
-) -> ObjectJson {
-    let (obj, obj_hash) = mine_object(WOOD_BLUEPRINT, WOOD_MINING_MAX, vec![]);
-    println!("Mined wood: seed={}, hash={}", obj.seed, obj_hash);
-
-    let mut wood_stdin = SP1Stdin::new();
-    wood_stdin.write(&ObjectInput {
-        object: obj.clone(),
-        work: hex::encode([0u8; 32]),
-    });
-
-    let start = std::time::Instant::now();
-    let wood_proof: SP1ProofWithPublicValues = client
-        .prove(wood_pk, &wood_stdin)
-        .compressed()
-        .run()
-        .expect("wood proving failed");
-    let duration = start.elapsed();
-    println!("Wood proving time: {:?}", duration);
-
-    client
-        .verify(&wood_proof, wood_vk)
-        .expect("wood verify failed");
-
-    let committed_output: ObjectOutput = wood_proof.public_values.clone().read();
-    println!("Wood committed hash: {}", committed_output.hash);
-
-    ObjectJson {
-        object: obj,
-        hash: committed_output.hash,
-        work: hex::encode([0u8; 32]),
-        proof: wood_proof,
-        program_vk: wood_vk.clone(),
-    }
-}
-
-fn create_axe_object(
-    client: &EnvProver,
-    axe_pk: &sp1_sdk::SP1ProvingKey,
-    axe_vk: &sp1_sdk::SP1VerifyingKey,
-    stone_vk: &sp1_sdk::SP1VerifyingKey,
-    wood_vk: &sp1_sdk::SP1VerifyingKey,
-    wood_hash: String,
-    wood_proof: SP1ProofWithPublicValues,
-    stone_hash: String,
-    stone_proof: SP1ProofWithPublicValues,
+/// Crafts a stack of `quantity` objects of `blueprint` of at least the tier `rarity`, following its
+/// recipe from `recipe_table`, consuming `inputs` and using `catalysts`, which the crafter keeps.
+fn create_object(
+    (client, cycles): (&EnvProver, bool),
+    (pow_pk, pow_vk): (&sp1_sdk::SP1ProvingKey, &sp1_sdk::SP1VerifyingKey),
+    (pk, vk): (&sp1_sdk::SP1ProvingKey, &sp1_sdk::SP1VerifyingKey),
+    recipe_table: &RecipeTable,
+    (blueprint, quantity, rarity): (Blueprint, u32, Rarity),
+    (owner, anchor): (OwnerKey, Option<[u8; 32]>),
+    (inputs, catalysts): (Vec<ObjectJson>, &[ObjectJson]),
 ) -> ObjectJson {
+    let recipe = recipe_table
+        .get(blueprint)
+        .unwrap_or_else(|| panic!("no recipe for {}", blueprint));
     let (obj, obj_hash) = mine_object(
-        AXE_BLUEPRINT,
-        AXE_MINING_MAX,
-        vec![wood_hash.clone(), stone_hash.clone()],
+        recipe,
+        (quantity, rarity),
+        (owner, anchor),
+        inputs.iter().map(|input| input.hash).collect(),
+        catalysts.iter().map(|catalyst| catalyst.hash).collect(),
     );
-    println!("Created axe: seed={}, hash={}", obj.seed, obj_hash);
-
-    let mut axe_stdin = SP1Stdin::new();
-    axe_stdin.write(&ObjectInput {
-        object: obj.clone(),
-        work: hex::encode([0u8; 32]),
+    println!("Mined {}: seed={}, hash={}", blueprint, obj.seed, obj_hash);
+
+    let pow = recipe.work.map(|work| {
+        let work = work.for_quantity(quantity);
+        println!("Creating POW proof for {} ({})...", blueprint, work);
+        let (pow_out, pow_proof) = create_pow_proof(client, pow_pk, pow_vk, work, obj_hash);
+        println!(
+            "Completed POW proof for {}, {}.",
+            blueprint,
+            hex::encode(pow_out.output)
+        );
+        (pow_out, pow_proof)
     });
+    let work = pow
+        .as_ref()
+        .map_or([0u8; 32], |(pow_out, _)| pow_out.output);
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&RecipeIn {
+        object: ObjectInput {
+            object: obj.clone(),
+            work,
+        },
+        derivation: Derivation::Craft(recipe.clone()),
+        recipe_table: recipe_table.ids(),
+        program: vk.hash_u32(),
+    });
+    if let Some((pow_out, pow_proof)) = pow {
+        stdin.write(&pow_out);
+        let SP1Proof::Compressed(compressed_proof) = pow_proof else {
+            panic!("expected compressed proof")
+        };
+        stdin.write_proof(*compressed_proof, pow_vk.vk.clone());
+    }
+    for object in inputs.iter().chain(catalysts) {
+        object.write_to(&mut stdin);
+    }
 
-    let wood_output: ObjectOutput = wood_proof.public_values.clone().read();
-    axe_stdin.write(&wood_output);
-
-    let SP1Proof::Compressed(wood_compressed) = wood_proof.proof else {
-        panic!("expected compressed proof")
-    };
-    axe_stdin.write_proof(*wood_compressed, wood_vk.clone().vk);
-
-    let stone_output: ObjectOutput = stone_proof.public_values.clone().read();
-    axe_stdin.write(&stone_output);
-
-    let SP1Proof::Compressed(stone_compressed) = stone_proof.proof else {
-        panic!("expected compressed proof")
-    };
-    axe_stdin.write_proof(*stone_compressed, stone_vk.clone().vk);
+    if cycles {
+        report_cycles(client, &blueprint.to_string(), RECIPE_ELF, &stdin);
+    }
 
     let start = std::time::Instant::now();
-    let axe_proof: SP1ProofWithPublicValues = client
-        .prove(axe_pk, &axe_stdin)
+    let proof: SP1ProofWithPublicValues = client
+        .prove(pk, &stdin)
         .compressed()
         .run()
-        .expect("axe proving failed");
+        .unwrap_or_else(|e| panic!("{} proving failed: {}", blueprint, e));
     let duration = start.elapsed();
-    println!("Axe proving time: {:?}", duration);
+    println!("{} proving time: {:?}", blueprint, duration);
 
     client
-        .verify(&axe_proof, axe_vk)
-        .expect("axe verify failed");
+        .verify(&proof, vk)
+        .unwrap_or_else(|e| panic!("{} verify failed: {}", blueprint, e));
 
-    let committed_output: ObjectOutput = axe_proof.public_values.clone().read();
-    println!("Axe committed hash: {}", committed_output.hash);
+    let committed_output: ObjectOutput = proof.public_values.clone().read();
+    println!(
+        "{} committed hash: {}, {}",
+        blueprint, committed_output.hash, committed_output.rarity
+    );
 
     ObjectJson {
         object: obj,
         hash: committed_output.hash,
-        work: hex::encode([0u8; 32]),
-        proof: axe_proof,
-        program_vk: axe_vk.clone(),
+        work,
+        proof,
+        program_vk: vk.clone(),
     }
 }
 
 fn main() {
     utils::setup_logger();
 
+    // The objects a recipe takes are passed with one flag per blueprint, like `--wood`.
+    let command = Cli::command().mut_subcommand("craft", |craft| {
+        Blueprint::ALL.into_iter().fold(craft, |craft, blueprint| {
+            craft.arg(
+                Arg::new(blueprint.def().name)
+                    .long(blueprint.def().name)
+                    .value_name("OBJECT")
+                    .action(ArgAction::Append)
+                    .help(format!(
+                        "{} object consumed or used by the recipe",
+                        blueprint
+                    )),
+            )
+        })
+    });
+    let matches = command.get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
     let client = ProverClient::from_env();
 
     println!("Setting up proving/verifying keys...");
     let (pow_pk, pow_vk) = client.setup(POW_ELF);
-    let (stone_pk, stone_vk) = client.setup(STONE_ELF);
-    let (wood_pk, wood_vk) = client.setup(WOOD_ELF);
-    let (axe_pk, axe_vk) = client.setup(AXE_ELF);
+    let (recipe_pk, recipe_vk) = client.setup(RECIPE_ELF);
     println!("pow program vk {}", hex::encode(pow_vk.hash_bytes()));
-    println!("wood program vk {}", hex::encode(wood_vk.hash_bytes()));
-    println!("stone program vk {}", hex::encode(stone_vk.hash_bytes()));
-    println!("axe program vk {}", hex::encode(axe_vk.hash_bytes()));
+    println!("recipe program vk {}", hex::encode(recipe_vk.hash_bytes()));
 
-    std::fs::create_dir_all("objects").expect("failed to create objects directory");
+    // Proofs of work are verified by the recipe program, so the pow vk must be the one that
+    // program was built with. The recipe program itself must be in the commit allowlist.
+    check_vk_digest("pow", &pow_vk, &POW_VKEY_DIGEST).expect("stale vkey");
 
-    let num_woods = 1;
-    let num_stones = 1;
-    let num_axes = 1;
+    let recipe_table = RecipeTable::current();
+    println!("recipe table {}", hex::encode(recipe_table.hash()));
 
-    let mut wood_objects = Vec::new();
-    let mut stone_objects = Vec::new();
+    let owner = owner_key(&load_owner_key().expect("failed to load owner key"));
+    println!("Crafting objects owned by {}", owner);
 
-    for i in 1..=num_woods {
-        println!("\n=== Creating Wood {} ===", i);
-        let object = create_wood_object(&client, &wood_pk, &wood_vk);
-        let basefilename = format!("objects/wood_{}", i);
-        let filename = format!("{}.json", basefilename);
-        object.save_as_json(&filename).expect("failed to save wood");
-        object
-            .save_as_bytes(format!("{}.bin", basefilename))
-            .expect("failed to save wood as bytes");
-        println!("Saved to {}", filename);
-        wood_objects.push(object);
-    }
+    let out_dir = &cli.out_dir;
+    std::fs::create_dir_all(out_dir).expect("failed to create output directory");
 
-    for i in 1..=num_stones {
-        println!("\n=== Creating Stone {} ===", i);
-        let object = create_stone_object(&client, &pow_pk, &pow_vk, &stone_pk, &stone_vk);
-        let basefilename = format!("objects/stone_{}", i);
-        let filename = format!("{}.json", basefilename);
+    // Objects are saved under the first free name for their blueprint, and stacks for their
+    // blueprint and quantity.
+    let craft = |(blueprint, quantity): (Blueprint, u32),
+                 inputs: Vec<ObjectJson>,
+                 catalysts: &[ObjectJson]| {
+        let prefix = match quantity {
+            1 => blueprint.to_string(),
+            _ => format!("{}_x{}", blueprint, quantity),
+        };
+        let name = next_name(out_dir, &prefix);
+        println!("\n=== Creating {} ===", name);
+        let object = create_object(
+            (&client, cli.cycles),
+            (&pow_pk, &pow_vk),
+            (&recipe_pk, &recipe_vk),
+            &recipe_table,
+            (blueprint, quantity, cli.rarity),
+            (owner, cli.anchor),
+            (inputs, catalysts),
+        );
+        let filename = out_dir.join(format!("{}.json", name));
         object
             .save_as_json(&filename)
-            .expect("failed to save stone");
+            .unwrap_or_else(|e| panic!("failed to save {}: {}", blueprint, e));
         object
-            .save_as_bytes(format!("{}.bin", basefilename))
-            .expect("failed to save stone as bytes");
-        println!("Saved to {}", filename);
-        stone_objects.push(object);
-    }
-
-    for i in 1..=num_axes {
-        println!("\n=== Creating Axe {} ===", i);
-        let wood_object = wood_objects.pop().expect("need wood for axe");
-        let stone_object = stone_objects.pop().expect("need stone for axe");
-
-        let object = create_axe_object(
-            &client,
-            &axe_pk,
-            &axe_vk,
-            &stone_vk,
-            &wood_vk,
-            wood_object.hash,
-            wood_object.proof,
-            stone_object.hash,
-            stone_object.proof,
-        );
-        let basefilename = format!("objects/axe_{}", i);
-        let filename = format!("{}.json", basefilename);
-        object.save_as_json(&filename).expect("failed to save axe");
+            .save_as_bytes(out_dir.join(format!("{}.bin", name)))
+            .unwrap_or_else(|e| panic!("failed to save {} as bytes: {}", blueprint, e));
+        println!("Saved to {}", filename.display());
         object
-            .save_as_bytes(format!("{}.bin", basefilename))
-            .expect("failed to save axe as bytes");
-        println!("Saved to {}", filename);
+    };
+
+    match cli.command {
+        Command::Mine {
+            blueprint,
+            count,
+            quantity,
+        } => {
+            if !blueprint.def().inputs.is_empty() || !blueprint.def().catalysts.is_empty() {
+                eprintln!(
+                    "{} is crafted from other objects and can't be mined, use craft",
+                    blueprint
+                );
+                std::process::exit(1);
+            }
+            for _ in 0..count {
+                craft((blueprint, quantity), vec![], &[]);
+            }
+        }
+        Command::Craft {
+            blueprint,
+            quantity,
+        } => {
+            let craft_matches = matches
+                .subcommand_matches("craft")
+                .expect("craft subcommand");
+            let recipe = recipe_table
+                .get(blueprint)
+                .unwrap_or_else(|| panic!("no recipe for {}", blueprint));
+            // Objects of the blueprints the recipe uses as catalysts are catalysts, and the others
+            // are inputs, which the recipe program checks against the recipe.
+            let (mut inputs, mut catalysts) = (Vec::new(), Vec::new());
+            for object_blueprint in Blueprint::ALL {
+                let paths = craft_matches
+                    .get_many::<String>(object_blueprint.def().name)
+                    .into_iter()
+                    .flatten();
+                for path in paths {
+                    let object = load_object(path);
+                    if object.object.blueprint != object_blueprint {
+                        eprintln!("{} is not a {}", path, object_blueprint);
+                        std::process::exit(1);
+                    }
+                    if recipe.catalysts.contains(&object_blueprint) {
+                        catalysts.push(object);
+                    } else {
+                        inputs.push(object);
+                    }
+                }
+            }
+            craft((blueprint, quantity), inputs, &catalysts);
+        }
+        Command::Demo => {
+            let mut woods: Vec<ObjectJson> = (0..2)
+                .map(|_| craft((Blueprint::Wood, 1), vec![], &[]))
+                .collect();
+            let stone = craft((Blueprint::Stone, 1), vec![], &[]);
+            let axe = craft(
+                (Blueprint::Axe, 1),
+                vec![woods.pop().expect("need wood for axe"), stone],
+                &[],
+            );
+            // The axe is a catalyst, so it isn't consumed, but it is worn when the plank is
+            // committed.
+            craft(
+                (Blueprint::Plank, 1),
+                vec![woods.pop().expect("need wood for plank")],
+                std::slice::from_ref(&axe),
+            );
+        }
     }
 
     println!("\n✓ All objects created successfully!");
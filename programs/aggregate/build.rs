@@ -0,0 +1,5 @@
+use utils::vkeys::generate_vkey_digests;
+
+fn main() {
+    generate_vkey_digests(&[("COMMIT", "../commit")]);
+}
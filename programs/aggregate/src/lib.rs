@@ -0,0 +1,29 @@
+use commit_program::CommitOut;
+use common::StateProof;
+use serde::{Deserialize, Serialize};
+
+/// Vkey digests of the programs whose proofs this program verifies, generated by `build.rs`.
+pub mod vkeys {
+    include!(concat!(env!("OUT_DIR"), "/vkeys.rs"));
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AggregateIn {
+    /// Ethereum address that will send the blob transaction, which every batch must be bound to.
+    pub committer: [u8; 20],
+    /// Committer's nonce, which must be above every nonce it used in an earlier commitment.
+    pub nonce: u64,
+    /// Public values of the commit proofs to aggregate, all applying to the same root.
+    pub batches: Vec<CommitOut>,
+    /// Proof of each of the merged [`CommitOut::state_updates`] against the root before it.
+    pub witnesses: Vec<StateProof>,
+}
+
+impl AggregateIn {
+    /// The merged batch, with the same public values as a single commit proof.
+    pub fn out(&self) -> CommitOut {
+        let mut out = CommitOut::merge(self.committer, self.nonce, &self.batches);
+        out.apply_witnesses(&self.witnesses);
+        out
+    }
+}
@@ -0,0 +1,25 @@
+//! A program that aggregates commit proofs into a single commitment
+
+// These two lines are necessary for the program to properly compile.
+//
+// Under the hood, we wrap your main function with some extra code so that it behaves properly
+// inside the zkVM.
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use aggregate_program::{vkeys::COMMIT_VKEY_DIGEST, AggregateIn};
+use common::guest;
+
+pub fn main() {
+    let inp = sp1_zkvm::io::read::<AggregateIn>();
+
+    for batch in inp.batches.iter() {
+        // Verify proof
+        guest::verify(&COMMIT_VKEY_DIGEST, batch);
+    }
+
+    // Also checks that the batches don't conflict.
+    let out = inp.out();
+
+    sp1_zkvm::io::commit(&out);
+}
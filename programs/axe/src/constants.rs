@@ -1,2 +0,0 @@
-pub const AXE_BLUEPRINT: &str = "axe";
-pub const AXE_MINING_MAX: u64 = 0x0020_0000_0000_0000;
@@ -0,0 +1,204 @@
+//! Allowlist of the programs whose object proofs the commit program accepts, each with the recipe
+//! table its objects were crafted from.
+//!
+//! The commit program only sees the Merkle root of the allowlist, so that allowing a new program
+//! or recipe table doesn't change its vk. Synchronizers decide which roots they accept.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use common::RecipeTable;
+
+const ENTRY_DOMAIN: &[u8] = b"digital-objects/allowlist-entry";
+const NODE_DOMAIN: &[u8] = b"digital-objects/allowlist-node";
+
+/// Object program, and recipe table of the objects it proves, whose objects may be committed.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct AllowlistEntry {
+    /// Vkey digest of the program.
+    pub program: [u32; 8],
+    /// [`common::recipe_table_hash`] of the table.
+    pub recipe_table: [u8; 32],
+}
+
+/// Merkle proof of an entry of an allowlist.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AllowlistProof {
+    /// Position of the entry, whose bit `l` is set if the path is on the right at level `l`.
+    index: u32,
+    /// Siblings of the path, from the entry up.
+    siblings: Vec<[u8; 32]>,
+}
+
+/// Entries of an allowlist, in order. The tree is padded with zero leaves to a power of two.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct Allowlist {
+    entries: Vec<AllowlistEntry>,
+}
+
+impl AllowlistEntry {
+    fn hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(ENTRY_DOMAIN);
+        for word in self.program {
+            hasher.update(word.to_be_bytes());
+        }
+        hasher.update(self.recipe_table);
+        hasher.finalize().into()
+    }
+}
+
+impl AllowlistProof {
+    /// Root of the allowlist with `entry` at this proof's position.
+    pub fn root(&self, entry: &AllowlistEntry) -> [u8; 32] {
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(entry.hash(), |cur, (level, sibling)| {
+                if (self.index >> level) & 1 == 1 {
+                    node(sibling, &cur)
+                } else {
+                    node(&cur, sibling)
+                }
+            })
+    }
+}
+
+impl Allowlist {
+    pub fn new(entries: Vec<AllowlistEntry>) -> Self {
+        Self { entries }
+    }
+
+    /// Allowlist of the recipe program with vkey digest `recipe_program` and the current recipe
+    /// table, see [`RecipeTable::current`].
+    pub fn current(recipe_program: [u32; 8]) -> Self {
+        Self::new(vec![AllowlistEntry {
+            program: recipe_program,
+            recipe_table: RecipeTable::current().hash(),
+        }])
+    }
+
+    pub fn entries(&self) -> &[AllowlistEntry] {
+        &self.entries
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.levels().last().expect("at least one level")[0]
+    }
+
+    /// Proof of `entry`, if it is in the allowlist.
+    pub fn prove(&self, entry: &AllowlistEntry) -> Option<AllowlistProof> {
+        let index = self.entries.iter().position(|e| e == entry)?;
+        let levels = self.levels();
+        let siblings = levels[..levels.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(level, hashes)| hashes[(index >> level) ^ 1])
+            .collect();
+        Some(AllowlistProof {
+            index: index as u32,
+            siblings,
+        })
+    }
+
+    /// Hashes of each level of the tree, from the padded entries up to the root.
+    fn levels(&self) -> Vec<Vec<[u8; 32]>> {
+        let mut level: Vec<[u8; 32]> = self.entries.iter().map(AllowlistEntry::hash).collect();
+        level.resize(self.entries.len().next_power_of_two(), [0; 32]);
+        let mut levels = vec![level];
+        while levels.last().expect("at least one level").len() > 1 {
+            let next = levels
+                .last()
+                .expect("at least one level")
+                .chunks(2)
+                .map(|pair| node(&pair[0], &pair[1]))
+                .collect();
+            levels.push(next);
+        }
+        levels
+    }
+}
+
+fn node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(NODE_DOMAIN);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u32) -> AllowlistEntry {
+        AllowlistEntry {
+            program: [id; 8],
+            recipe_table: [id as u8; 32],
+        }
+    }
+
+    fn allowlist(n: u32) -> Allowlist {
+        Allowlist::new((0..n).map(entry).collect())
+    }
+
+    #[test]
+    fn single_entry_is_its_own_root() {
+        let allowlist = allowlist(1);
+        let proof = allowlist.prove(&entry(0)).expect("entry is allowed");
+        assert!(proof.siblings.is_empty());
+        assert_eq!(allowlist.root(), entry(0).hash());
+        assert_eq!(proof.root(&entry(0)), allowlist.root());
+    }
+
+    #[test]
+    fn proves_every_entry() {
+        for n in 1..=9 {
+            let allowlist = allowlist(n);
+            for id in 0..n {
+                let proof = allowlist.prove(&entry(id)).expect("entry is allowed");
+                assert_eq!(
+                    proof.siblings.len(),
+                    n.next_power_of_two().trailing_zeros() as usize
+                );
+                assert_eq!(proof.root(&entry(id)), allowlist.root());
+            }
+        }
+    }
+
+    #[test]
+    fn pads_with_zero_leaves() {
+        let root = node(
+            &node(&entry(0).hash(), &entry(1).hash()),
+            &node(&entry(2).hash(), &[0; 32]),
+        );
+        assert_eq!(allowlist(3).root(), root);
+    }
+
+    #[test]
+    fn root_depends_on_every_entry() {
+        let mut entries: Vec<AllowlistEntry> = (0..3).map(entry).collect();
+        let root = Allowlist::new(entries.clone()).root();
+        entries[2].recipe_table = [9; 32];
+        assert_ne!(Allowlist::new(entries).root(), root);
+    }
+
+    #[test]
+    fn rejects_entries_not_in_the_list() {
+        let allowlist = allowlist(3);
+        // Another table for an allowed program, and the allowed table for another program.
+        let other_table = AllowlistEntry {
+            recipe_table: [9; 32],
+            ..entry(1)
+        };
+        let other_program = AllowlistEntry {
+            program: [9; 8],
+            ..entry(1)
+        };
+        let proof = allowlist.prove(&entry(1)).expect("entry is allowed");
+        for other in [other_table, other_program] {
+            assert!(allowlist.prove(&other).is_none());
+            assert_ne!(proof.root(&other), allowlist.root());
+        }
+    }
+}
@@ -1,20 +1,1206 @@
-use common::ObjectHash;
+use std::collections::{BTreeMap, BTreeSet};
+
+use common::{
+    Leaf, Object, ObjectHash, ObjectOutput, OwnerKey, OwnerSignature, Rarity, StateProof,
+    StateUpdate, RESTACKED_RECIPE, WORN_RECIPE,
+};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+mod allowlist;
+mod swap;
+pub use allowlist::{Allowlist, AllowlistEntry, AllowlistProof};
+pub use swap::{SignedSwap, Swap};
+
+const COMMIT_DIGEST_DOMAIN: &[u8] = b"digital-objects/commit-batch";
+const COMMITMENT_DOMAIN: &[u8] = b"digital-objects/commitment";
+const PRIVATE_TAG_DOMAIN: &[u8] = b"digital-objects/private-tag";
+const NULLIFIER_DOMAIN: &[u8] = b"digital-objects/nullifier";
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OwnedObject {
+    pub hash: ObjectHash,
+    pub owner: OwnerKey,
+}
+
+/// Handoff of a live object from its current owner to a new one, signed by `from`.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Transfer {
+    pub hash: ObjectHash,
+    pub from: OwnerKey,
+    pub to: OwnerKey,
+}
+
+/// Proof that the committer may consume an object.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum Holder {
+    /// The object is public and owned by this key, which must sign the batch.
+    Owner(OwnerKey),
+    /// The object is private and committed to with the tag of this secret.
+    Secret([u8; 32]),
+}
+
+/// Split or merge of stacks: `sources` are consumed and `pieces` are created, all of the same
+/// blueprint and with the same total quantity. Each piece takes the sources as its inputs.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Restack {
+    pub sources: Vec<ObjectOutput>,
+    pub pieces: Vec<Object>,
+}
 
+/// Wear of a tool used as a catalyst by the objects of a batch: each object wears it by its
+/// quantity, and the tool is replaced by `successor`, or worn out if no durability is left.
 #[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct ObjectOutputWithType {
+pub struct Wear {
+    pub tool: ObjectOutput,
+    /// Current owner of the tool, who keeps its successor.
+    pub owner: OwnerKey,
+    /// Successor of the tool, taking it as its only input, or `None` if the tool wore out.
+    pub successor: Option<Object>,
+}
+
+/// Tool worn by a batch, linked to its successor, or marked worn out if there is none.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WornTool {
+    pub hash: ObjectHash,
+    pub owner: OwnerKey,
+    pub successor: Option<ObjectHash>,
+}
+
+/// Public object created above the common tier.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RareObject {
     pub hash: ObjectHash,
-    pub consumed: Vec<ObjectHash>,
-    pub blueprint: String,
+    pub rarity: Rarity,
+}
+
+/// Private object, published as a key that only those who know its hash can link to it.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Commitment {
+    /// [`commitment_key`] of the object hash.
+    pub key: [u8; 32],
+    /// [`private_tag`] of the object hash and the holder's secret.
+    pub tag: [u8; 32],
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct CommitIn {
-    pub objects: Vec<ObjectOutputWithType>,
+    /// Ethereum address that will send the blob transaction with this batch.
+    pub committer: [u8; 20],
+    /// Committer's nonce, which must be above every nonce it used in an earlier batch.
+    pub nonce: u64,
+    /// Whether the batch is only to be committed as part of an aggregate by `committer`, whose
+    /// nonce then supersedes `nonce`.
+    pub aggregated: bool,
+    /// State root the batch applies to.
+    pub prev_root: [u8; 32],
+    /// Root of the allowlist of the object programs and recipe tables.
+    pub allowlist: [u8; 32],
+    pub objects: Vec<ObjectOutput>,
+    pub restacks: Vec<Restack>,
+    /// Wear of each tool used by `objects`.
+    pub wears: Vec<Wear>,
+    /// Proof that the program and recipe table of each of [`CommitIn::verified_objects`] are in
+    /// `allowlist`.
+    pub allowlist_proofs: Vec<AllowlistProof>,
+    /// For each of `objects`, then each restacked piece, the tag to create it privately with, or
+    /// `None` to create it publicly.
+    pub created_tags: Vec<Option<[u8; 32]>>,
+    /// Holder of each object consumed by `objects`, then of each restacked source, in order.
+    pub consumed_holders: Vec<Holder>,
+    /// Current owner of each object referenced by `objects`, in order.
+    pub referenced_owners: Vec<OwnerKey>,
+    pub transfers: Vec<Transfer>,
+    /// Swaps signed by all their owners, who don't need to sign the batch.
+    pub swaps: Vec<SignedSwap>,
+    /// Objects destroyed by their owners without creating anything.
+    pub burns: Vec<OwnedObject>,
+    /// Proof of each of [`CommitIn::state_updates`] against the root before it.
+    pub witnesses: Vec<StateProof>,
+    /// Signatures of [`CommitOut::digest`], one by each of [`CommitOut::owners`] and
+    /// [`CommitIn::private_owners`].
+    pub signatures: Vec<(OwnerKey, OwnerSignature)>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct CommitOut {
-    pub created: Vec<ObjectHash>,
-    pub consumed: Vec<ObjectHash>,
+    /// Only this address may commit the batch, so that a seen proof can't be front-run.
+    pub committer: [u8; 20],
+    pub nonce: u64,
+    /// Whether the batch can only be committed through the aggregate program, which clears the
+    /// flag of the merged batch. Synchronizers reject commit proofs of batches with it set.
+    pub aggregated: bool,
+    /// State root before the batch, which must be the current one for the batch to apply.
+    pub prev_root: [u8; 32],
+    /// State root after the batch.
+    pub new_root: [u8; 32],
+    /// Root of the allowlist the objects were proved by, which the synchronizer must accept.
+    pub allowlist: [u8; 32],
+    /// Beacon block roots the public created objects were mined after, which the synchronizer may
+    /// require to be recent. Private objects don't publish theirs, which would tell when they were
+    /// mined, and so are exempt from that requirement.
+    pub anchors: Vec<[u8; 32]>,
+    pub created: Vec<OwnedObject>,
+    /// Tiers of the created objects that are not common. Private objects don't reveal theirs.
+    pub rare: Vec<RareObject>,
+    pub consumed: Vec<OwnedObject>,
+    /// Objects created privately.
+    pub commitments: Vec<Commitment>,
+    /// Nullifiers of the objects consumed privately, which can only be derived with their secret.
+    pub nullifiers: Vec<[u8; 32]>,
+    /// Objects used as catalysts, which must be live but are not consumed.
+    pub referenced: Vec<OwnedObject>,
+    /// Tools used as catalysts, which are replaced by their successors or marked worn out.
+    pub worn: Vec<WornTool>,
+    pub transferred: Vec<Transfer>,
+    /// Swaps committed, each authorized by the signatures of its owners rather than the batch's.
+    pub swaps: Vec<Swap>,
+    /// Objects burned by their owners, which are consumed without being used by a recipe.
+    pub burned: Vec<OwnedObject>,
+}
+
+impl CommitIn {
+    /// The public values of the commit program for this batch. This doesn't check any proof or
+    /// signature, so that owners can compute the digest to sign before proving.
+    ///
+    /// It does check that the program and recipe table of each object are in the allowlist, that
+    /// restacks conserve quantities, and that the batch is consistent on its own, and sorts its
+    /// public lists:
+    /// - no object is created or consumed twice, or transferred twice;
+    /// - no object is burned twice, or burned and otherwise used within the batch;
+    /// - every swap is between several owners, and commits all its legs;
+    /// - no object is created and then consumed, referenced or transferred within the batch;
+    /// - no catalyst is consumed or transferred within the batch;
+    /// - every tool used as a catalyst is worn once, by the uses of all objects, and is not
+    ///   otherwise used within the batch.
+    ///
+    /// A catalyst may be referenced by several objects, and appears once in `referenced`.
+    ///
+    /// It then applies the batch to `prev_root`, checking each state update against its witness.
+    pub fn out(&self) -> CommitOut {
+        let (mut out, private_reads) = self.batch();
+        let updates = [private_reads, out.state_updates()].concat();
+        out.new_root = apply_witnesses(&out.prev_root, &updates, &self.witnesses);
+        out
+    }
+
+    /// State updates of the batch, in the order the witnesses must prove them: the private
+    /// reads, which are not published, then [`CommitOut::state_updates`].
+    pub fn state_updates(&self) -> Vec<StateUpdate> {
+        let (out, private_reads) = self.batch();
+        [private_reads, out.state_updates()].concat()
+    }
+
+    /// Objects whose proofs the commit program verifies: `objects`, then the restacked sources,
+    /// then the worn tools.
+    pub fn verified_objects(&self) -> impl Iterator<Item = &ObjectOutput> {
+        self.objects
+            .iter()
+            .chain(
+                self.restacks
+                    .iter()
+                    .flat_map(|restack| restack.sources.iter()),
+            )
+            .chain(self.wears.iter().map(|wear| &wear.tool))
+    }
+
+    /// The batch before it is applied to the state, with `new_root` still equal to `prev_root`,
+    /// and the state reads that private objects require.
+    fn batch(&self) -> (CommitOut, Vec<StateUpdate>) {
+        let verified: Vec<&ObjectOutput> = self.verified_objects().collect();
+        assert_eq!(
+            verified.len(),
+            self.allowlist_proofs.len(),
+            "Must have one allowlist proof per verified object"
+        );
+        for (object, proof) in verified.iter().zip(self.allowlist_proofs.iter()) {
+            let entry = AllowlistEntry {
+                program: object.program,
+                recipe_table: object.recipe_table,
+            };
+            assert!(
+                proof.root(&entry) == self.allowlist,
+                "Program and recipe table of object {} are not in the allowlist",
+                object.hash
+            );
+        }
+        for object in &self.objects {
+            assert!(
+                object.recipe != RESTACKED_RECIPE,
+                "Restacked object {} can only be created by a restack",
+                object.hash
+            );
+            assert!(
+                object.recipe != WORN_RECIPE,
+                "Worn tool {} can only be created by a wear",
+                object.hash
+            );
+        }
+        for restack in &self.restacks {
+            restack.check();
+        }
+
+        let created_list = self.created_list();
+        let mut private_reads = Vec::new();
+
+        let mut created_hashes = BTreeSet::new();
+        let mut created = Vec::new();
+        let mut commitments = Vec::new();
+        for (object, tag) in created_list.iter().zip(self.created_tags.iter()) {
+            let hash = object.hash;
+            assert!(
+                created_hashes.insert(hash),
+                "Object {} is created twice",
+                hash
+            );
+            match tag {
+                None => created.push(OwnedObject {
+                    hash,
+                    owner: object.owner,
+                }),
+                Some(tag) => {
+                    // A private object must not exist publicly, and the reverse is checked by
+                    // the public state updates.
+                    private_reads.push(StateUpdate {
+                        key: hash,
+                        old: Leaf::Empty,
+                        new: Leaf::Empty,
+                    });
+                    commitments.push(Commitment {
+                        key: commitment_key(&hash),
+                        tag: *tag,
+                    });
+                }
+            }
+        }
+
+        // Restacked pieces are common, and successors keep the tier of their tool.
+        let mut rare: Vec<RareObject> = self
+            .objects
+            .iter()
+            .zip(self.created_tags.iter())
+            .filter(|(object, tag)| tag.is_none() && object.rarity != Rarity::Common)
+            .map(|(object, _)| RareObject {
+                hash: object.hash,
+                rarity: object.rarity,
+            })
+            .collect();
+
+        // Each object wears the tools it uses by its quantity.
+        let mut uses: BTreeMap<ObjectHash, u64> = BTreeMap::new();
+        for object in &self.objects {
+            for hash in &object.worn {
+                *uses.entry(*hash).or_default() += object.quantity as u64;
+            }
+        }
+        let mut worn = Vec::new();
+        for wear in &self.wears {
+            let hash = wear.tool.hash;
+            let used = uses
+                .remove(&hash)
+                .unwrap_or_else(|| panic!("Tool {} is not used, or worn twice", hash));
+            let successor = wear.check(used);
+            if let Some(successor) = successor {
+                assert!(
+                    created_hashes.insert(successor),
+                    "Object {} is created twice",
+                    successor
+                );
+                created.push(OwnedObject {
+                    hash: successor,
+                    owner: wear.owner,
+                });
+                if wear.tool.rarity != Rarity::Common {
+                    rare.push(RareObject {
+                        hash: successor,
+                        rarity: wear.tool.rarity,
+                    });
+                }
+            }
+            worn.push(WornTool {
+                hash,
+                owner: wear.owner,
+                successor,
+            });
+        }
+        if let Some(hash) = uses.keys().next() {
+            panic!("Tool {} is used without being worn", hash);
+        }
+        created.sort();
+        rare.sort();
+        commitments.sort();
+        worn.sort();
+
+        let consumed_list: Vec<ObjectHash> = self
+            .objects
+            .iter()
+            .flat_map(|object| object.consumed.iter().copied())
+            .chain(
+                self.restacks
+                    .iter()
+                    .flat_map(|restack| restack.sources.iter().map(|source| source.hash)),
+            )
+            .collect();
+        assert_eq!(
+            consumed_list.len(),
+            self.consumed_holders.len(),
+            "Must have one holder per consumed object"
+        );
+        let mut consumed_hashes = BTreeSet::new();
+        let mut consumed = Vec::new();
+        let mut nullifiers = Vec::new();
+        for (hash, holder) in consumed_list.iter().zip(self.consumed_holders.iter()) {
+            assert!(
+                consumed_hashes.insert(*hash),
+                "Object {} is consumed twice",
+                hash
+            );
+            match holder {
+                Holder::Owner(owner) => consumed.push(OwnedObject {
+                    hash: *hash,
+                    owner: *owner,
+                }),
+                Holder::Secret(secret) => {
+                    let committed = Leaf::Committed(private_tag(hash, secret));
+                    private_reads.push(StateUpdate {
+                        key: commitment_key(hash).into(),
+                        old: committed,
+                        new: committed,
+                    });
+                    nullifiers.push(nullifier(hash, secret));
+                }
+            }
+        }
+        consumed.sort();
+        nullifiers.sort();
+
+        let mut referenced = with_owners(
+            self.objects
+                .iter()
+                .flat_map(|object| object.referenced.iter().copied()),
+            &self.referenced_owners,
+        );
+        referenced.sort();
+        referenced.dedup();
+        for pair in referenced.windows(2) {
+            assert!(
+                pair[0].hash != pair[1].hash,
+                "Catalyst {} is claimed by two owners",
+                pair[0].hash
+            );
+        }
+
+        let mut swaps: Vec<Swap> = self.swaps.iter().map(|s| s.swap.clone()).collect();
+        swaps.sort();
+        for pair in swaps.windows(2) {
+            assert!(pair[0] != pair[1], "Swap is committed twice");
+        }
+        for swap in &swaps {
+            swap.check();
+        }
+
+        let mut transfers = self.transfers.clone();
+        transfers.sort();
+        let mut transferred = BTreeSet::new();
+        let legs = swaps.iter().flat_map(|swap| swap.legs.iter());
+        for transfer in transfers.iter().chain(legs) {
+            assert!(
+                transferred.insert(transfer.hash),
+                "Object {} is transferred twice",
+                transfer.hash
+            );
+        }
+
+        for hash in &consumed_hashes {
+            assert!(
+                !created_hashes.contains(hash),
+                "Object {} is created and consumed in the same batch",
+                hash
+            );
+        }
+        for object in &referenced {
+            assert!(
+                !created_hashes.contains(&object.hash),
+                "Catalyst {} is created in the same batch",
+                object.hash
+            );
+            assert!(
+                !consumed_hashes.contains(&object.hash),
+                "Catalyst {} is consumed in the same batch",
+                object.hash
+            );
+        }
+        for hash in &transferred {
+            assert!(
+                !created_hashes.contains(hash)
+                    && !consumed_hashes.contains(hash)
+                    && !contains(&referenced, *hash),
+                "Object {} is transferred and used in the same batch",
+                hash
+            );
+        }
+        let mut burned = self.burns.clone();
+        burned.sort();
+        for pair in burned.windows(2) {
+            assert!(
+                pair[0].hash != pair[1].hash,
+                "Object {} is burned twice",
+                pair[0].hash
+            );
+        }
+        for object in &burned {
+            assert!(
+                !created_hashes.contains(&object.hash)
+                    && !consumed_hashes.contains(&object.hash)
+                    && !contains(&referenced, object.hash)
+                    && !transferred.contains(&object.hash)
+                    && worn.iter().all(|tool| tool.hash != object.hash),
+                "Object {} is burned and otherwise used in the same batch",
+                object.hash
+            );
+        }
+        for tool in &worn {
+            assert!(
+                !created_hashes.contains(&tool.hash)
+                    && !consumed_hashes.contains(&tool.hash)
+                    && !contains(&referenced, tool.hash)
+                    && !transferred.contains(&tool.hash),
+                "Tool {} is worn and otherwise used in the same batch",
+                tool.hash
+            );
+        }
+
+        // Private objects don't reveal their anchors, which would link them to when they were mined.
+        let mut anchors: Vec<[u8; 32]> = self
+            .objects
+            .iter()
+            .zip(self.created_tags.iter())
+            .filter(|(_, tag)| tag.is_none())
+            .filter_map(|(object, _)| object.anchor)
+            .collect();
+        anchors.sort();
+        anchors.dedup();
+
+        let out = CommitOut {
+            committer: self.committer,
+            nonce: self.nonce,
+            aggregated: self.aggregated,
+            prev_root: self.prev_root,
+            new_root: self.prev_root,
+            allowlist: self.allowlist,
+            anchors,
+            created,
+            rare,
+            consumed,
+            commitments,
+            nullifiers,
+            referenced,
+            worn,
+            transferred: transfers,
+            swaps,
+            burned,
+        };
+        (out, private_reads)
+    }
+
+    /// Objects created by `objects`, then by each restack, with their owners, in the order of
+    /// `created_tags`.
+    fn created_list(&self) -> Vec<OwnedObject> {
+        let created_list: Vec<OwnedObject> = self
+            .objects
+            .iter()
+            .map(|object| OwnedObject {
+                hash: object.hash,
+                owner: object.owner,
+            })
+            .chain(self.restacks.iter().flat_map(|restack| {
+                restack.pieces.iter().map(|piece| OwnedObject {
+                    hash: piece.hash(),
+                    owner: piece.owner,
+                })
+            }))
+            .collect();
+        assert_eq!(
+            created_list.len(),
+            self.created_tags.len(),
+            "Must have one tag option per created object"
+        );
+        created_list
+    }
+
+    /// Owners of the objects created privately, who must sign the batch like
+    /// [`CommitOut::owners`], so that an object can't be committed to someone else's secret. They
+    /// are only checked against `signatures`, and never published.
+    pub fn private_owners(&self) -> BTreeSet<OwnerKey> {
+        self.created_list()
+            .into_iter()
+            .zip(self.created_tags.iter())
+            .filter(|(_, tag)| tag.is_some())
+            .map(|(object, _)| object.owner)
+            .collect()
+    }
+
+    /// Checks that `signatures` has a valid signature of `out`'s digest by each of
+    /// [`CommitOut::owners`] and [`CommitIn::private_owners`].
+    pub fn check_signatures(&self, out: &CommitOut) {
+        let digest = out.digest();
+        for owner in out.owners().into_iter().chain(self.private_owners()) {
+            assert!(
+                self.signatures
+                    .iter()
+                    .any(|(key, signature)| *key == owner && key.verify(&digest, signature)),
+                "Missing signature by owner {}",
+                owner
+            );
+        }
+    }
+}
+
+impl Restack {
+    /// Checks that the pieces are stacks of the sources' blueprint taking them as inputs, and that
+    /// quantities are conserved.
+    fn check(&self) {
+        let first = self.sources.first().expect("Must restack some objects");
+        assert!(!self.pieces.is_empty(), "Must restack into some pieces");
+        let inputs: Vec<ObjectHash> = self.sources.iter().map(|source| source.hash).collect();
+        for source in &self.sources {
+            assert!(
+                source.blueprint == first.blueprint,
+                "Restacked objects must share a blueprint"
+            );
+            assert!(source.durability.is_none(), "Tools can't be stacked");
+        }
+        for piece in &self.pieces {
+            assert!(
+                piece.blueprint == first.blueprint,
+                "Restacked objects must share a blueprint"
+            );
+            assert!(piece.quantity > 0, "Quantity must be positive");
+            assert!(piece.durability.is_none(), "Tools can't be stacked");
+            assert!(
+                piece.inputs == inputs && piece.catalysts.is_empty(),
+                "Pieces must take the restacked objects as inputs"
+            );
+        }
+        let sources: u64 = self.sources.iter().map(|s| s.quantity as u64).sum();
+        let pieces: u64 = self.pieces.iter().map(|p| p.quantity as u64).sum();
+        assert!(
+            sources == pieces,
+            "Restack of {} {} into {} is not conserved",
+            sources,
+            first.blueprint,
+            pieces
+        );
+    }
+}
+
+impl Wear {
+    /// Checks that the tool has the durability for `uses`, and that its successor, if any, is the
+    /// same tool with the durability left, and returns the successor's hash.
+    fn check(&self, uses: u64) -> Option<ObjectHash> {
+        let tool = &self.tool;
+        let durability = tool
+            .durability
+            .unwrap_or_else(|| panic!("Object {} is not a tool", tool.hash));
+        assert!(
+            uses <= durability as u64,
+            "Tool {} is used {} times with {} uses left",
+            tool.hash,
+            uses,
+            durability
+        );
+        let left = durability - uses as u32;
+        let successor = match &self.successor {
+            None if left == 0 => return None,
+            None => panic!(
+                "Tool {} must have a successor with {} uses left",
+                tool.hash, left
+            ),
+            Some(successor) => successor,
+        };
+        assert!(
+            left > 0,
+            "Worn out tool {} can't have a successor",
+            tool.hash
+        );
+        assert!(
+            successor.blueprint == tool.blueprint
+                && successor.quantity == 1
+                && successor.durability == Some(left),
+            "Successor of tool {} must be a {} with {} uses left",
+            tool.hash,
+            tool.blueprint,
+            left
+        );
+        assert!(
+            successor.inputs == [tool.hash] && successor.catalysts.is_empty(),
+            "Successor of tool {} must take it as its only input",
+            tool.hash
+        );
+        assert!(
+            successor.owner == self.owner,
+            "Successor of tool {} must be owned by its owner",
+            tool.hash
+        );
+        Some(successor.hash())
+    }
+}
+
+impl CommitOut {
+    /// Merges batches proved against the same root and allowlist into a single batch committed by
+    /// `committer`, which each batch must be bound to and only be committed through. The batches
+    /// conflict, and can't be merged, if they change the same state key or read different values
+    /// of it. Their own nonces are superseded by `nonce`.
+    ///
+    /// `new_root` is left equal to `prev_root`, see [`CommitOut::apply_witnesses`].
+    pub fn merge(committer: [u8; 20], nonce: u64, batches: &[CommitOut]) -> CommitOut {
+        let first = batches.first().expect("Must merge at least one batch");
+        let (prev_root, allowlist) = (first.prev_root, first.allowlist);
+        let mut merged = CommitOut {
+            committer,
+            nonce,
+            aggregated: false,
+            prev_root,
+            new_root: prev_root,
+            allowlist,
+            anchors: vec![],
+            created: vec![],
+            rare: vec![],
+            consumed: vec![],
+            commitments: vec![],
+            nullifiers: vec![],
+            referenced: vec![],
+            worn: vec![],
+            transferred: vec![],
+            swaps: vec![],
+            burned: vec![],
+        };
+
+        let mut touched: BTreeMap<ObjectHash, StateUpdate> = BTreeMap::new();
+        for batch in batches {
+            assert!(
+                batch.prev_root == prev_root,
+                "Batches must apply to the same root"
+            );
+            assert!(
+                batch.allowlist == allowlist,
+                "Batches must be proved against the same allowlist"
+            );
+            assert!(
+                batch.committer == committer,
+                "Batch is bound to another committer"
+            );
+            assert!(batch.aggregated, "Batch is not bound to an aggregate");
+            for update in batch.state_updates() {
+                if let Some(other) = touched.insert(update.key, update) {
+                    assert!(
+                        other == update && update.old == update.new,
+                        "Batches conflict on {}",
+                        update.key
+                    );
+                }
+            }
+
+            merged.anchors.extend_from_slice(&batch.anchors);
+            merged.created.extend_from_slice(&batch.created);
+            merged.rare.extend_from_slice(&batch.rare);
+            merged.consumed.extend_from_slice(&batch.consumed);
+            merged.commitments.extend_from_slice(&batch.commitments);
+            merged.nullifiers.extend_from_slice(&batch.nullifiers);
+            merged.referenced.extend_from_slice(&batch.referenced);
+            merged.worn.extend_from_slice(&batch.worn);
+            merged.transferred.extend_from_slice(&batch.transferred);
+            merged.swaps.extend_from_slice(&batch.swaps);
+            merged.burned.extend_from_slice(&batch.burned);
+        }
+        merged.anchors.sort();
+        merged.anchors.dedup();
+        merged.created.sort();
+        merged.rare.sort();
+        merged.consumed.sort();
+        merged.commitments.sort();
+        merged.nullifiers.sort();
+        merged.referenced.sort();
+        merged.referenced.dedup();
+        merged.worn.sort();
+        merged.transferred.sort();
+        merged.swaps.sort();
+        merged.burned.sort();
+        merged
+    }
+
+    /// Applies the batch to `prev_root`, checking each of [`CommitOut::state_updates`] against its
+    /// witness, and sets `new_root`.
+    pub fn apply_witnesses(&mut self, witnesses: &[StateProof]) {
+        self.new_root = apply_witnesses(&self.prev_root, &self.state_updates(), witnesses);
+    }
+
+    /// Digest of the batch that the owners sign.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(COMMIT_DIGEST_DOMAIN);
+        hasher.update(bincode::serialize(self).expect("serialize CommitOut"));
+        hasher.finalize().into()
+    }
+
+    /// Changes of the state made by the batch: catalysts are read, then consumed objects are
+    /// marked as such, worn tools are linked to their successors or marked worn out, burned objects are
+    /// marked as such, nullifiers are added, transferred and swapped objects change owner, swap
+    /// keys are marked, and created objects and commitments are added. A public object is only
+    /// created if it has no commitment.
+    pub fn state_updates(&self) -> Vec<StateUpdate> {
+        let referenced = self.referenced.iter().map(|object| StateUpdate {
+            key: object.hash,
+            old: Leaf::Live(object.owner),
+            new: Leaf::Live(object.owner),
+        });
+        let consumed = self.consumed.iter().map(|object| StateUpdate {
+            key: object.hash,
+            old: Leaf::Live(object.owner),
+            new: Leaf::Consumed,
+        });
+        let worn = self.worn.iter().map(|tool| StateUpdate {
+            key: tool.hash,
+            old: Leaf::Live(tool.owner),
+            new: tool.successor.map_or(Leaf::WornOut, Leaf::Worn),
+        });
+        let transferred = self.transferred.iter().map(|transfer| StateUpdate {
+            key: transfer.hash,
+            old: Leaf::Live(transfer.from),
+            new: Leaf::Live(transfer.to),
+        });
+        let burned = self.burned.iter().map(|object| StateUpdate {
+            key: object.hash,
+            old: Leaf::Live(object.owner),
+            new: Leaf::Burned,
+        });
+        // A swap's key can only be marked once, so that it can't be replayed.
+        let swapped = self.swaps.iter().flat_map(|swap| {
+            let key = StateUpdate {
+                key: swap.key().into(),
+                old: Leaf::Empty,
+                new: Leaf::Nullifier,
+            };
+            std::iter::once(key).chain(swap.legs.iter().map(|leg| StateUpdate {
+                key: leg.hash,
+                old: Leaf::Live(leg.from),
+                new: Leaf::Live(leg.to),
+            }))
+        });
+        let nullifiers = self.nullifiers.iter().map(|nullifier| StateUpdate {
+            key: (*nullifier).into(),
+            old: Leaf::Empty,
+            new: Leaf::Nullifier,
+        });
+        let created = self.created.iter().flat_map(|object| {
+            [
+                StateUpdate {
+                    key: commitment_key(&object.hash).into(),
+                    old: Leaf::Empty,
+                    new: Leaf::Empty,
+                },
+                StateUpdate {
+                    key: object.hash,
+                    old: Leaf::Empty,
+                    new: Leaf::Live(object.owner),
+                },
+            ]
+        });
+        let commitments = self.commitments.iter().map(|commitment| StateUpdate {
+            key: commitment.key.into(),
+            old: Leaf::Empty,
+            new: Leaf::Committed(commitment.tag),
+        });
+        referenced
+            .chain(consumed)
+            .chain(worn)
+            .chain(burned)
+            .chain(nullifiers)
+            .chain(transferred)
+            .chain(swapped)
+            .chain(created)
+            .chain(commitments)
+            .collect()
+    }
+
+    /// Owners of the public created, consumed, referenced, worn, transferred and burned objects,
+    /// all of which must sign the batch. Private objects are consumed with their secret instead,
+    /// and the owners of those created are kept private, see [`CommitIn::private_owners`].
+    pub fn owners(&self) -> BTreeSet<OwnerKey> {
+        self.created
+            .iter()
+            .chain(self.consumed.iter())
+            .chain(self.referenced.iter())
+            .chain(self.burned.iter())
+            .map(|object| object.owner)
+            .chain(self.worn.iter().map(|tool| tool.owner))
+            .chain(self.transferred.iter().map(|transfer| transfer.from))
+            .collect()
+    }
+}
+
+/// Root after applying `updates` to `root`, each proved by the witness at the same index.
+fn apply_witnesses(root: &[u8; 32], updates: &[StateUpdate], witnesses: &[StateProof]) -> [u8; 32] {
+    assert_eq!(
+        updates.len(),
+        witnesses.len(),
+        "Must have one witness per state update"
+    );
+    updates
+        .iter()
+        .zip(witnesses.iter())
+        .fold(*root, |root, (update, witness)| {
+            witness.update(&root, update)
+        })
+}
+
+/// Pairs each object hash with its claimed current owner.
+fn with_owners(hashes: impl Iterator<Item = ObjectHash>, owners: &[OwnerKey]) -> Vec<OwnedObject> {
+    let hashes: Vec<ObjectHash> = hashes.collect();
+    assert_eq!(hashes.len(), owners.len(), "Must have one owner per object");
+    hashes
+        .into_iter()
+        .zip(owners.iter())
+        .map(|(hash, owner)| OwnedObject {
+            hash,
+            owner: *owner,
+        })
+        .collect()
+}
+
+/// Whether `objects`, sorted by hash, contain `hash`.
+fn contains(objects: &[OwnedObject], hash: ObjectHash) -> bool {
+    objects
+        .binary_search_by_key(&hash, |object| object.hash)
+        .is_ok()
+}
+
+/// State key of the commitment to a private object, derived from its hash alone so that an object
+/// can only be committed once.
+pub fn commitment_key(hash: &ObjectHash) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(COMMITMENT_DOMAIN);
+    hasher.update(hash.0);
+    hasher.finalize().into()
+}
+
+/// Tag binding a private object to `secret`, which differs between objects of the same holder.
+pub fn private_tag(hash: &ObjectHash, secret: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(PRIVATE_TAG_DOMAIN);
+    hasher.update(secret);
+    hasher.update(hash.0);
+    hasher.finalize().into()
+}
+
+/// Nullifier published when the private object is consumed, unlinkable to its commitment
+/// without `secret`.
+pub fn nullifier(hash: &ObjectHash, secret: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(NULLIFIER_DOMAIN);
+    hasher.update(secret);
+    hasher.update(hash.0);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use common::{Blueprint, StateTree};
+    use k256::ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey};
+
+    use super::*;
+
+    fn signing_key(byte: u8) -> SigningKey {
+        SigningKey::from_slice(&[byte; 32]).expect("valid signing key")
+    }
+
+    fn owner_key(key: &SigningKey) -> OwnerKey {
+        OwnerKey::from(key.verifying_key())
+    }
+
+    fn sign(key: &SigningKey, digest: &[u8; 32]) -> (OwnerKey, OwnerSignature) {
+        let signature: Signature = key.sign_prehash(digest).expect("sign digest");
+        (owner_key(key), OwnerSignature::from(&signature))
+    }
+
+    /// Wood with hash `[id; 32]` owned by `owner`, crafted out of `consumed`.
+    fn object(id: u8, owner: OwnerKey, consumed: &[ObjectHash]) -> ObjectOutput {
+        ObjectOutput {
+            hash: ObjectHash([id; 32]),
+            owner,
+            blueprint: Blueprint::Wood,
+            quantity: 1,
+            durability: None,
+            rarity: Rarity::Common,
+            anchor: Some([id; 32]),
+            recipe: [1; 32],
+            recipe_table: [2; 32],
+            program: [3; 8],
+            consumed: consumed.to_vec(),
+            referenced: vec![],
+            worn: vec![],
+        }
+    }
+
+    /// Batch creating `objects` with `created_tags` and consuming their inputs from `owner`,
+    /// proved against a state where only the inputs are live.
+    fn commit_in(
+        objects: Vec<ObjectOutput>,
+        created_tags: Vec<Option<[u8; 32]>>,
+        owner: OwnerKey,
+    ) -> CommitIn {
+        let allowlist = Allowlist::new(vec![AllowlistEntry {
+            program: [3; 8],
+            recipe_table: [2; 32],
+        }]);
+        let consumed: Vec<ObjectHash> = objects
+            .iter()
+            .flat_map(|object| object.consumed.iter().copied())
+            .collect();
+        let mut state = StateTree::default();
+        state.apply(
+            &BTreeSet::from_iter(consumed.iter())
+                .into_iter()
+                .map(|hash| StateUpdate {
+                    key: *hash,
+                    old: Leaf::Empty,
+                    new: Leaf::Live(owner),
+                })
+                .collect::<Vec<_>>(),
+        );
+        let mut inp = CommitIn {
+            committer: [4; 20],
+            nonce: 1,
+            aggregated: false,
+            prev_root: state.root(),
+            allowlist: allowlist.root(),
+            allowlist_proofs: objects
+                .iter()
+                .map(|object| {
+                    allowlist
+                        .prove(&AllowlistEntry {
+                            program: object.program,
+                            recipe_table: object.recipe_table,
+                        })
+                        .expect("object is allowed")
+                })
+                .collect(),
+            objects,
+            restacks: vec![],
+            wears: vec![],
+            created_tags,
+            consumed_holders: vec![Holder::Owner(owner); consumed.len()],
+            referenced_owners: vec![],
+            transfers: vec![],
+            swaps: vec![],
+            burns: vec![],
+            witnesses: vec![],
+            signatures: vec![],
+        };
+        inp.witnesses = state.apply(&inp.state_updates());
+        inp
+    }
+
+    /// Stack of `quantity` woods with hash `[id; 32]`.
+    fn stack(id: u8, quantity: u32) -> ObjectOutput {
+        ObjectOutput {
+            quantity,
+            ..object(id, owner_key(&signing_key(1)), &[])
+        }
+    }
+
+    /// Restack of `sources` into pieces of `quantities` woods.
+    fn restack(sources: Vec<ObjectOutput>, quantities: &[u32]) -> Restack {
+        let pieces = quantities
+            .iter()
+            .enumerate()
+            .map(|(i, &quantity)| Object {
+                key: [i as u8; 32],
+                owner: owner_key(&signing_key(1)),
+                inputs: sources.iter().map(|source| source.hash).collect(),
+                catalysts: vec![],
+                seed: 0,
+                blueprint: Blueprint::Wood,
+                quantity,
+                durability: None,
+                anchor: None,
+            })
+            .collect();
+        Restack { sources, pieces }
+    }
+
+    #[test]
+    fn restacks_conserve_quantities() {
+        restack(vec![stack(5, 10)], &[7, 3]).check();
+        restack(vec![stack(5, 4), stack(6, 6)], &[10]).check();
+        restack(vec![stack(5, 4), stack(6, 6)], &[1, 2, 7]).check();
+    }
+
+    #[test]
+    #[should_panic(expected = "is not conserved")]
+    fn split_must_conserve_quantities() {
+        restack(vec![stack(5, 10)], &[7, 4]).check();
+    }
+
+    #[test]
+    #[should_panic(expected = "is not conserved")]
+    fn merge_must_conserve_quantities() {
+        restack(vec![stack(5, 4), stack(6, 6)], &[9]).check();
+    }
+
+    #[test]
+    #[should_panic(expected = "Quantity must be positive")]
+    fn restack_rejects_empty_pieces() {
+        restack(vec![stack(5, 10)], &[10, 0]).check();
+    }
+
+    #[test]
+    #[should_panic(expected = "Restacked objects must share a blueprint")]
+    fn restack_sources_must_share_a_blueprint() {
+        let stone = ObjectOutput {
+            blueprint: Blueprint::Stone,
+            ..stack(6, 6)
+        };
+        restack(vec![stack(5, 4), stone], &[10]).check();
+    }
+
+    #[test]
+    #[should_panic(expected = "Restacked objects must share a blueprint")]
+    fn restack_pieces_must_share_a_blueprint() {
+        let mut restack = restack(vec![stack(5, 10)], &[7, 3]);
+        restack.pieces[1].blueprint = Blueprint::Stone;
+        restack.check();
+    }
+
+    #[test]
+    #[should_panic(expected = "is consumed twice")]
+    fn input_consumed_twice_is_rejected() {
+        let owner = owner_key(&signing_key(1));
+        let stone = ObjectHash([9; 32]);
+        let first = object(5, owner, &[stone]);
+        let second = object(6, owner, &[stone]);
+        commit_in(vec![first, second], vec![None, None], owner);
+    }
+
+    #[test]
+    #[should_panic(expected = "is created and consumed in the same batch")]
+    fn object_created_and_consumed_is_rejected() {
+        let owner = owner_key(&signing_key(1));
+        let wood = object(5, owner, &[]);
+        let plank = object(6, owner, &[wood.hash]);
+        commit_in(vec![wood, plank], vec![None, None], owner);
+    }
+
+    #[test]
+    #[should_panic(expected = "is created twice")]
+    fn object_created_twice_is_rejected() {
+        let owner = owner_key(&signing_key(1));
+        let wood = object(5, owner, &[]);
+        commit_in(vec![wood.clone(), wood], vec![None, None], owner);
+    }
+
+    #[test]
+    fn created_and_consumed_are_sorted() {
+        let owner = owner_key(&signing_key(1));
+        let inputs = [0x13, 0x11, 0x14, 0x12].map(|id| ObjectHash([id; 32]));
+        let objects = vec![
+            object(7, owner, &inputs[..2]),
+            object(5, owner, &inputs[2..3]),
+            object(6, owner, &inputs[3..]),
+        ];
+        let hashes = |ids: &[u8]| {
+            ids.iter()
+                .map(|&id| OwnedObject {
+                    hash: ObjectHash([id; 32]),
+                    owner,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let (out, _) = commit_in(objects, vec![None, None, None], owner).batch();
+        assert_eq!(out.created, hashes(&[5, 6, 7]));
+        assert_eq!(out.consumed, hashes(&[0x11, 0x12, 0x13, 0x14]));
+    }
+
+    #[test]
+    fn worn_out_tools_are_not_burned() {
+        let owner = owner_key(&signing_key(1));
+        let (mut out, _) = commit_in(vec![], vec![], owner).batch();
+        out.worn = vec![
+            WornTool {
+                hash: ObjectHash([5; 32]),
+                owner,
+                successor: Some(ObjectHash([6; 32])),
+            },
+            WornTool {
+                hash: ObjectHash([7; 32]),
+                owner,
+                successor: None,
+            },
+        ];
+        out.burned = vec![OwnedObject {
+            hash: ObjectHash([8; 32]),
+            owner,
+        }];
+
+        let leaves: Vec<_> = out
+            .state_updates()
+            .into_iter()
+            .map(|update| (update.key, update.new))
+            .collect();
+        assert_eq!(
+            leaves,
+            vec![
+                (ObjectHash([5; 32]), Leaf::Worn(ObjectHash([6; 32]))),
+                (ObjectHash([7; 32]), Leaf::WornOut),
+                (ObjectHash([8; 32]), Leaf::Burned),
+            ]
+        );
+    }
+
+    #[test]
+    fn transfers_are_sorted() {
+        let owner = owner_key(&signing_key(1));
+        let transfers: Vec<Transfer> = [7, 5, 6]
+            .map(|id| Transfer {
+                hash: ObjectHash([id; 32]),
+                from: owner,
+                to: owner_key(&signing_key(2)),
+            })
+            .to_vec();
+        let mut inp = commit_in(vec![], vec![], owner);
+        inp.transfers = transfers.clone();
+
+        let (out, _) = inp.batch();
+        let mut sorted = transfers;
+        sorted.sort();
+        assert_eq!(out.transferred, sorted);
+    }
+
+    #[test]
+    fn private_object_is_committed_by_its_owner() {
+        let key = signing_key(1);
+        let owner = owner_key(&key);
+        let wood = object(5, owner, &[]);
+        let mut inp = commit_in(vec![wood], vec![Some([6; 32])], owner);
+
+        let out = inp.out();
+        assert!(out.owners().is_empty());
+        assert!(out.anchors.is_empty(), "private anchors are not published");
+        inp.signatures.push(sign(&key, &out.digest()));
+        inp.check_signatures(&out);
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing signature by owner")]
+    fn private_object_of_someone_else_is_rejected() {
+        let victim = owner_key(&signing_key(1));
+        let attacker = signing_key(2);
+        let wood = object(5, victim, &[]);
+        let mut inp = commit_in(vec![wood], vec![Some([6; 32])], victim);
+
+        let out = inp.out();
+        inp.signatures.push(sign(&attacker, &out.digest()));
+        inp.check_signatures(&out);
+    }
 }
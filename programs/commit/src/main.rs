@@ -1,4 +1,4 @@
-//! A proof of work program
+//! A commit object program
 
 // These two lines are necessary for the program to properly compile.
 //
@@ -7,58 +7,29 @@
 #![no_main]
 sp1_zkvm::entrypoint!(main);
 
-use axe_program::constants::AXE_BLUEPRINT;
-use commit_program::{CommitIn, CommitOut};
-use common::{hex_to_vk_digest, ObjectHash, ObjectOutput};
-use sha2::{Digest, Sha256};
-use stone_program::constants::STONE_BLUEPRINT;
-use wood_program::constants::WOOD_BLUEPRINT;
-
-// TODO: find a way to auto-generate and share these constants, also store without having to decode
-const WOOD_VKEY_HASH: &str = "1dc5f3be73dbe87875287d81592e666159cd2bb91b9a30a54e4c70c570523f45";
-const STONE_VKEY_HASH: &str = "5d0865c4708a2df7685dee2f0c98e52d32674eee126a276c127276bd6e3d7ad2";
-const AXE_VKEY_HASH: &str = "2656118a5e39427a0943d08447d372cf1c6d276b396f90f31cbc87706db3c6ca";
+use commit_program::CommitIn;
+use common::guest;
 
 pub fn main() {
     let inp = sp1_zkvm::io::read::<CommitIn>();
 
-    let mut created: Vec<ObjectHash> = Vec::new();
-    let mut consumed: Vec<ObjectHash> = Vec::new();
+    for object in inp.verified_objects() {
+        // Verify proof. The recipe program verified the object's inputs and catalysts against
+        // the same vkey, and whether it is allowed is checked against the allowlist.
+        guest::verify(&object.program, object);
+    }
+
+    // Also checks that the objects are allowed and that the batch is consistent on its own.
+    let out = inp.out();
+    // Including the owners of the objects created privately, whose signatures are not published.
+    inp.check_signatures(&out);
 
-    for object in inp.objects.iter() {
-        let object_output = ObjectOutput {
-            hash: object.hash.clone(),
-            consumed: object.consumed.clone(),
-        };
-        let object_output_digest: [u8; 32] =
-            Sha256::digest(&bincode::serialize(&object_output).unwrap()).into();
-        // Verify proof
-        match object.blueprint.as_str() {
-            WOOD_BLUEPRINT => {
-                sp1_zkvm::lib::verify::verify_sp1_proof(
-                    &hex_to_vk_digest(WOOD_VKEY_HASH),
-                    &object_output_digest,
-                );
-            }
-            STONE_BLUEPRINT => {
-                sp1_zkvm::lib::verify::verify_sp1_proof(
-                    &hex_to_vk_digest(STONE_VKEY_HASH),
-                    &object_output_digest,
-                );
-            }
-            AXE_BLUEPRINT => {
-                sp1_zkvm::lib::verify::verify_sp1_proof(
-                    &hex_to_vk_digest(AXE_VKEY_HASH),
-                    &object_output_digest,
-                );
-            }
-            _ => panic!("unknown blueprint"),
-        }
-        created.push(object.hash.clone());
-        for inp in object.consumed.iter() {
-            consumed.push(inp.clone());
+    // Swaps are signed by their owners on their own, see `commit_program::Swap`.
+    for swap in &inp.swaps {
+        if let Some(owner) = swap.missing_signatures().first() {
+            panic!("Missing signature of a swap by owner {}", owner);
         }
     }
 
-    sp1_zkvm::io::commit(&CommitOut { created, consumed });
+    sp1_zkvm::io::commit(&out);
 }
@@ -0,0 +1,89 @@
+//! Swaps of objects between owners, committed all at once or not at all.
+//!
+//! Each owner giving objects signs the swap itself rather than the batch committing it, so that an
+//! offer can be signed by one owner and countersigned by the others before the batch is proved.
+//! The signatures cover every leg, so the swap is only valid with all of them.
+
+use std::collections::BTreeSet;
+
+use common::{OwnerKey, OwnerSignature};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::Transfer;
+
+const SWAP_DIGEST_DOMAIN: &[u8] = b"digital-objects/swap";
+const SWAP_KEY_DOMAIN: &[u8] = b"digital-objects/swap-key";
+
+/// Exchange of objects between two or more owners, each of whom transfers objects to the others.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Swap {
+    /// Random value making each offer unique, so that a committed swap can't be replayed once the
+    /// objects are back with their owners.
+    pub salt: [u8; 32],
+    pub legs: Vec<Transfer>,
+}
+
+/// Swap with the signatures of its owners, see [`Swap::digest`].
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SignedSwap {
+    pub swap: Swap,
+    pub signatures: Vec<(OwnerKey, OwnerSignature)>,
+}
+
+impl Swap {
+    /// Digest of the swap that each of its owners signs.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(SWAP_DIGEST_DOMAIN);
+        hasher.update(bincode::serialize(self).expect("serialize Swap"));
+        hasher.finalize().into()
+    }
+
+    /// State key marked once the swap is committed.
+    pub fn key(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(SWAP_KEY_DOMAIN);
+        hasher.update(self.digest());
+        hasher.finalize().into()
+    }
+
+    /// Owners giving objects in the swap.
+    pub fn parties(&self) -> BTreeSet<OwnerKey> {
+        self.legs.iter().map(|leg| leg.from).collect()
+    }
+
+    /// Checks that the swap is between at least two owners, and that each leg goes from one of
+    /// them to another.
+    pub fn check(&self) {
+        let parties = self.parties();
+        assert!(
+            parties.len() >= 2,
+            "Swap must be between at least two owners"
+        );
+        for leg in &self.legs {
+            assert!(
+                leg.to != leg.from && parties.contains(&leg.to),
+                "Swap of {} must go to another owner of the swap",
+                leg.hash
+            );
+        }
+    }
+}
+
+impl SignedSwap {
+    /// Owners of the swap without a valid signature of it.
+    pub fn missing_signatures(&self) -> Vec<OwnerKey> {
+        let digest = self.swap.digest();
+        self.swap
+            .parties()
+            .into_iter()
+            .filter(|party| {
+                !self
+                    .signatures
+                    .iter()
+                    .any(|(key, signature)| key == party && key.verify(&digest, signature))
+            })
+            .collect()
+    }
+}
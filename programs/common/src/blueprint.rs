@@ -0,0 +1,127 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{PowFunction, Recipe, Target, Work};
+
+/// The kinds of objects that can be crafted.
+///
+/// The discriminants are the stable ids used in the canonical object encoding.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum Blueprint {
+    Wood = 1,
+    Stone = 2,
+    Axe = 3,
+    Plank = 4,
+}
+
+/// Registry entry describing how an object of a blueprint is crafted.
+///
+/// Every blueprint is crafted by the recipe program, which enforces the blueprint's
+/// [`Recipe`] from the table in [`crate::RecipeTable::current`].
+#[derive(Debug)]
+pub struct BlueprintDef {
+    pub name: &'static str,
+    /// Target the hash of a valid object must meet.
+    pub target: Target,
+    /// Proof of work required on top of mining, if any.
+    pub work: Option<Work>,
+    /// Blueprints of the objects consumed by the recipe, repeated for multiple copies.
+    pub inputs: &'static [Blueprint],
+    /// Blueprints of the objects the crafter must hold but which are not consumed, like tools.
+    pub catalysts: &'static [Blueprint],
+    /// Number of uses of the objects as catalysts before they wear out, if they are tools.
+    pub durability: Option<u32>,
+}
+
+const WOOD: BlueprintDef = BlueprintDef {
+    name: "wood",
+    target: Target::from_leading_zeros(11),
+    work: None,
+    inputs: &[],
+    catalysts: &[],
+    durability: None,
+};
+
+const STONE: BlueprintDef = BlueprintDef {
+    name: "stone",
+    target: Target::from_leading_zeros(11),
+    work: Some(Work {
+        function: PowFunction::Sha256,
+        n_iters: 3,
+    }),
+    inputs: &[],
+    catalysts: &[],
+    durability: None,
+};
+
+const AXE: BlueprintDef = BlueprintDef {
+    name: "axe",
+    target: Target::from_leading_zeros(11),
+    work: None,
+    inputs: &[Blueprint::Wood, Blueprint::Stone],
+    catalysts: &[],
+    durability: Some(4),
+};
+
+const PLANK: BlueprintDef = BlueprintDef {
+    name: "plank",
+    target: Target::from_leading_zeros(11),
+    work: None,
+    inputs: &[Blueprint::Wood],
+    catalysts: &[Blueprint::Axe],
+    durability: None,
+};
+
+impl Blueprint {
+    pub const ALL: [Blueprint; 4] = [
+        Blueprint::Wood,
+        Blueprint::Stone,
+        Blueprint::Axe,
+        Blueprint::Plank,
+    ];
+
+    pub const fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub const fn def(self) -> &'static BlueprintDef {
+        match self {
+            Blueprint::Wood => &WOOD,
+            Blueprint::Stone => &STONE,
+            Blueprint::Axe => &AXE,
+            Blueprint::Plank => &PLANK,
+        }
+    }
+
+    pub fn recipe(self) -> Recipe {
+        let def = self.def();
+        Recipe {
+            blueprint: self,
+            target: def.target,
+            work: def.work,
+            inputs: def.inputs.to_vec(),
+            catalysts: def.catalysts.to_vec(),
+            durability: def.durability,
+        }
+    }
+}
+
+impl fmt::Display for Blueprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.def().name)
+    }
+}
+
+impl FromStr for Blueprint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Blueprint::ALL
+            .into_iter()
+            .find(|blueprint| blueprint.def().name == s)
+            .ok_or_else(|| format!("unknown blueprint {}", s))
+    }
+}
@@ -0,0 +1,21 @@
+//! Helpers for verifying proofs of other programs from inside the zkVM.
+
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Reads a value from the prover and verifies that it is the public values of a proof of the
+/// program with vkey digest `vk`.
+///
+/// The proof itself must have been written to the stdin with `SP1Stdin::write_proof`.
+pub fn read_verified<T: Serialize + DeserializeOwned>(vk: &[u32; 8]) -> T {
+    let value = sp1_zkvm::io::read::<T>();
+    verify(vk, &value);
+    value
+}
+
+/// Verifies that `value` is the public values of a proof of the program with vkey digest `vk`.
+pub fn verify<T: Serialize>(vk: &[u32; 8], value: &T) {
+    let digest: [u8; 32] =
+        Sha256::digest(bincode::serialize(value).expect("serialize public values")).into();
+    sp1_zkvm::lib::verify::verify_sp1_proof(vk, &digest);
+}
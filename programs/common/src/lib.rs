@@ -1,57 +1,179 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
-pub type ObjectHash = String;
+mod blueprint;
+mod bytes;
+#[cfg(feature = "zkvm")]
+pub mod guest;
+mod hash;
+mod owner;
+mod rarity;
+mod recipe;
+mod state;
+mod target;
+mod work;
+pub use blueprint::{Blueprint, BlueprintDef};
+pub use hash::ObjectHash;
+pub use owner::{OwnerKey, OwnerSignature};
+pub use rarity::Rarity;
+pub use recipe::{
+    match_catalysts, match_inputs, recipe_table_hash, Recipe, RecipeTable, RESTACKED_RECIPE,
+    WORN_RECIPE,
+};
+pub use state::{Leaf, StateProof, StateTree, StateUpdate, EMPTY_ROOT};
+pub use target::Target;
+pub use work::{PowFunction, Work};
+
+/// Domain separation tag prefixed to every object hash preimage.
+pub const OBJECT_HASH_DOMAIN: &[u8] = b"digital-objects/object";
+/// Version of [`Object::canonical_bytes`], bumped whenever the encoding changes so that objects
+/// hashed under different encodings can never share an identity.
+pub const OBJECT_HASH_VERSION: u8 = 6;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Object {
-    pub key: String,
+    #[serde(with = "bytes")]
+    pub key: [u8; 32],
+    pub owner: OwnerKey,
     pub inputs: Vec<ObjectHash>,
+    /// Objects the recipe references without consuming them.
+    pub catalysts: Vec<ObjectHash>,
     pub seed: u32,
-    pub blueprint: String, // TODO: change to enum
+    pub blueprint: Blueprint,
+    /// Number of objects of the blueprint this object stands for, at least one.
+    pub quantity: u32,
+    /// Uses left before the object wears out, if it is a tool. A tool is replaced by a successor
+    /// with less durability each time it is used as a catalyst, and worn out when none is left.
+    pub durability: Option<u32>,
+    /// Recent beacon block root the object was mined after, so that it can't have been mined
+    /// before that block. Synchronizers may reject anchors that are too old.
+    pub anchor: Option<[u8; 32]>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct ObjectInput {
     pub object: Object,
-    pub work: String,
+    pub work: [u8; 32],
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct ObjectOutput {
     pub hash: ObjectHash,
+    pub owner: OwnerKey,
+    pub blueprint: Blueprint,
+    pub quantity: u32,
+    pub durability: Option<u32>,
+    /// Tier of the object's hash against its recipe's target. Restacked objects are common, and
+    /// worn tools keep the tier of the tool.
+    pub rarity: Rarity,
+    pub anchor: Option<[u8; 32]>,
+    /// [`Recipe::id`] of the recipe the object was crafted with, or [`RESTACKED_RECIPE`] or
+    /// [`WORN_RECIPE`].
+    #[serde(with = "bytes")]
+    pub recipe: [u8; 32],
+    /// [`recipe_table_hash`] of the table the recipe was taken from.
+    #[serde(with = "bytes")]
+    pub recipe_table: [u8; 32],
+    /// Vkey digest the recipe program verified the inputs and catalysts against, which must be
+    /// its own since a program can't embed its own vkey. Checked by whoever verifies the object.
+    pub program: [u32; 8],
     pub consumed: Vec<ObjectHash>,
+    /// Catalysts without durability, which are referenced as they are.
+    pub referenced: Vec<ObjectHash>,
+    /// Catalysts with durability, each worn by `quantity` uses when the object is committed.
+    pub worn: Vec<ObjectHash>,
 }
 
 impl Object {
-    pub fn hash(&self) -> String {
-        let bytes = bincode::serialize(&self.clone()).expect("serialize Object");
-        let digest: [u8; 32] = Sha256::digest(&bytes).into();
-        hex::encode(digest)
+    /// Canonical encoding of the object, independent of any serialization format:
+    ///
+    /// ```text
+    /// key (32) || owner (33) || blueprint id (1) || quantity (4, BE)
+    ///     || durability (0 if none, 1 || 4, BE) || anchor (0 if none, 1 || 32) || seed (4, BE)
+    ///     || number of inputs (4, BE) || inputs (32 each)
+    ///     || number of catalysts (4, BE) || catalysts (32 each)
+    /// ```
+    ///
+    /// Optional fields are prefixed with a presence byte, so that a missing field never encodes
+    /// like a present one.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(120 + 32 * (self.inputs.len() + self.catalysts.len()));
+        bytes.extend_from_slice(&self.key);
+        bytes.extend_from_slice(&self.owner.0);
+        bytes.push(self.blueprint.id());
+        bytes.extend_from_slice(&self.quantity.to_be_bytes());
+        match self.durability {
+            Some(durability) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&durability.to_be_bytes());
+            }
+            None => bytes.push(0),
+        }
+        match self.anchor {
+            Some(anchor) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&anchor);
+            }
+            None => bytes.push(0),
+        }
+        bytes.extend_from_slice(&self.seed.to_be_bytes());
+        bytes.extend_from_slice(&(self.inputs.len() as u32).to_be_bytes());
+        for input in &self.inputs {
+            bytes.extend_from_slice(&input.0);
+        }
+        bytes.extend_from_slice(&(self.catalysts.len() as u32).to_be_bytes());
+        for catalyst in &self.catalysts {
+            bytes.extend_from_slice(&catalyst.0);
+        }
+        bytes
     }
-}
 
-pub fn difficulty(hash: &str) -> u64 {
-    let bytes = hex::decode(hash).expect("valid hex hash");
-    u64::from_be_bytes(bytes[0..8].try_into().unwrap())
+    /// `SHA-256(OBJECT_HASH_DOMAIN || OBJECT_HASH_VERSION || canonical_bytes)`
+    pub fn hash(&self) -> ObjectHash {
+        let mut hasher = Sha256::new();
+        hasher.update(OBJECT_HASH_DOMAIN);
+        hasher.update([OBJECT_HASH_VERSION]);
+        hasher.update(self.canonical_bytes());
+        ObjectHash(hasher.finalize().into())
+    }
 }
 
-pub fn hex_to_vk_digest(hex_str: &str) -> [u32; 8] {
-    bytes_to_words_be(
-        hex::decode(hex_str)
-            .expect("valid hex string")
-            .as_slice()
-            .try_into()
-            .expect("32 bytes for vkey hash"),
-    )
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(durability: Option<u32>, anchor: Option<[u8; 32]>) -> Object {
+        Object {
+            key: [1; 32],
+            owner: OwnerKey([2; 33]),
+            inputs: vec![ObjectHash([3; 32])],
+            catalysts: vec![ObjectHash([4; 32])],
+            seed: 5,
+            blueprint: Blueprint::Stone,
+            quantity: 3,
+            durability,
+            anchor,
+        }
+    }
+
+    #[test]
+    fn hash_matches_test_vector() {
+        assert_eq!(
+            hex::encode(object(Some(10), Some([6; 32])).hash().0),
+            "156acec1d43c74116c7a278fb09c4d71a161d60f7fc25ef5505034763957c639"
+        );
+        assert_eq!(
+            hex::encode(object(None, None).hash().0),
+            "e12277d684559ce6d0454d348ad2d0b315ea4fe61833cc92f6c43e6805fe52d6"
+        );
+    }
 
-/// Utility method for converting 32 big-endian bytes back into eight u32 words.
-fn bytes_to_words_be(bytes: &[u8; 32]) -> [u32; 8] {
-    let mut words = [0u32; 8];
-    for i in 0..8 {
-        let chunk: [u8; 4] = bytes[i * 4..(i + 1) * 4].try_into().unwrap();
-        words[i] = u32::from_be_bytes(chunk);
+    #[test]
+    fn missing_fields_never_encode_like_present_ones() {
+        assert_ne!(object(None, None).hash(), object(Some(0), None).hash());
+        assert_ne!(
+            object(None, None).hash(),
+            object(None, Some([0; 32])).hash()
+        );
     }
-    words
 }
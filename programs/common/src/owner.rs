@@ -0,0 +1,66 @@
+use std::{fmt, str::FromStr};
+
+use k256::ecdsa::{signature::hazmat::PrehashVerifier, Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+/// Compressed SEC1 encoding of the secp256k1 public key owning an object.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct OwnerKey(#[serde(with = "crate::bytes")] pub [u8; 33]);
+
+/// ECDSA signature by an [`OwnerKey`], as the 64 byte `r || s` encoding.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OwnerSignature(#[serde(with = "crate::bytes")] pub [u8; 64]);
+
+impl OwnerKey {
+    /// Returns whether `signature` is a valid signature of `digest` by this key.
+    pub fn verify(&self, digest: &[u8; 32], signature: &OwnerSignature) -> bool {
+        let Ok(key) = VerifyingKey::from_sec1_bytes(&self.0) else {
+            return false;
+        };
+        let Ok(signature) = Signature::from_slice(&signature.0) else {
+            return false;
+        };
+        key.verify_prehash(digest, &signature).is_ok()
+    }
+}
+
+impl From<&VerifyingKey> for OwnerKey {
+    fn from(key: &VerifyingKey) -> Self {
+        let point = key.to_encoded_point(true);
+        Self(point.as_bytes().try_into().expect("33 byte compressed key"))
+    }
+}
+
+impl From<&Signature> for OwnerSignature {
+    fn from(signature: &Signature) -> Self {
+        Self(signature.to_bytes().into())
+    }
+}
+
+impl fmt::Display for OwnerKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl fmt::Debug for OwnerKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl FromStr for OwnerKey {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = [0u8; 33];
+        hex::decode_to_slice(s.trim_start_matches("0x"), &mut bytes)?;
+        Ok(Self(bytes))
+    }
+}
+
+impl fmt::Debug for OwnerSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
@@ -0,0 +1,68 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ObjectHash, Target};
+
+/// Rarity tier of a mined object, derived from how far its hash beats the target of its recipe.
+#[derive(
+    Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord,
+)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum Rarity {
+    /// Meets the target.
+    #[default]
+    Common = 0,
+    /// Meets a target 16 times stricter.
+    Rare = 1,
+    /// Meets a target 256 times stricter.
+    Golden = 2,
+}
+
+impl Rarity {
+    pub const ALL: [Rarity; 3] = [Rarity::Common, Rarity::Rare, Rarity::Golden];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rarity::Common => "common",
+            Rarity::Rare => "rare",
+            Rarity::Golden => "golden",
+        }
+    }
+
+    /// Target the hash of an object of this tier must meet, for objects mined against `target`.
+    pub fn target(self, target: &Target) -> Target {
+        match self {
+            Rarity::Common => *target,
+            Rarity::Rare => target.for_quantity(1 << 4),
+            Rarity::Golden => target.for_quantity(1 << 8),
+        }
+    }
+
+    /// Highest tier whose target `hash` meets, for a hash that meets `target`.
+    pub fn of(target: &Target, hash: &ObjectHash) -> Self {
+        Rarity::ALL
+            .into_iter()
+            .rev()
+            .find(|rarity| rarity.target(target).is_met_by(hash))
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Rarity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Rarity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rarity::ALL
+            .into_iter()
+            .find(|rarity| rarity.name() == s)
+            .ok_or_else(|| format!("unknown rarity {}", s))
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{Blueprint, ObjectHash, ObjectOutput, Target, Work};
+
+const RECIPE_DOMAIN: &[u8] = b"digital-objects/recipe";
+const RECIPE_TABLE_DOMAIN: &[u8] = b"digital-objects/recipe-table";
+
+/// Recipe id of the stacks split or merged from their inputs by the commit program, which checks
+/// that quantities are conserved. No recipe hashes to it.
+pub const RESTACKED_RECIPE: [u8; 32] = [0; 32];
+
+/// Recipe id of the successors of tools worn by the commit program, which checks that their
+/// durability went down by the uses of the tool. No recipe hashes to it.
+pub const WORN_RECIPE: [u8; 32] = [0xff; 32];
+
+/// How objects of a blueprint are crafted, enforced by the recipe program.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Recipe {
+    pub blueprint: Blueprint,
+    /// Target the hash of a valid object must meet.
+    pub target: Target,
+    /// Proof of work required on top of mining, if any.
+    pub work: Option<Work>,
+    /// Blueprints of the objects consumed, repeated for multiple copies.
+    pub inputs: Vec<Blueprint>,
+    /// Blueprints of the objects referenced but not consumed, like tools.
+    pub catalysts: Vec<Blueprint>,
+    /// Durability of the objects crafted, if they are tools.
+    pub durability: Option<u32>,
+}
+
+/// Recipes accepted by the commit program. Adding an item only takes a new entry in the table,
+/// not a new program.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct RecipeTable {
+    pub recipes: Vec<Recipe>,
+}
+
+impl Recipe {
+    /// Canonical encoding of the recipe:
+    ///
+    /// ```text
+    /// blueprint id (1) || target (32) || work function id (1, 0 if none) || work iterations (4, BE)
+    ///     || number of inputs (4, BE) || input blueprint ids (1 each)
+    ///     || number of catalysts (4, BE) || catalyst blueprint ids (1 each)
+    ///     || durability (0 if none, 1 || 4, BE)
+    /// ```
+    ///
+    /// Like in [`crate::Object::canonical_bytes`], durability is prefixed with a presence byte, so
+    /// that a recipe without durability never encodes like one with zero durability.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(51 + self.inputs.len() + self.catalysts.len());
+        bytes.push(self.blueprint.id());
+        bytes.extend_from_slice(&self.target.0);
+        let (function, n_iters) = self
+            .work
+            .map_or((0, 0), |work| (work.function as u8, work.n_iters));
+        bytes.push(function);
+        bytes.extend_from_slice(&n_iters.to_be_bytes());
+        bytes.extend_from_slice(&(self.inputs.len() as u32).to_be_bytes());
+        bytes.extend(self.inputs.iter().map(|input| input.id()));
+        bytes.extend_from_slice(&(self.catalysts.len() as u32).to_be_bytes());
+        bytes.extend(self.catalysts.iter().map(|catalyst| catalyst.id()));
+        match self.durability {
+            Some(durability) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&durability.to_be_bytes());
+            }
+            None => bytes.push(0),
+        }
+        bytes
+    }
+
+    /// `SHA-256(RECIPE_DOMAIN || canonical_bytes)`
+    pub fn id(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(RECIPE_DOMAIN);
+        hasher.update(self.canonical_bytes());
+        hasher.finalize().into()
+    }
+}
+
+impl RecipeTable {
+    /// Table of the recipes of every blueprint, as registered in [`Blueprint::def`].
+    pub fn current() -> Self {
+        Self {
+            recipes: Blueprint::ALL.iter().map(|b| b.recipe()).collect(),
+        }
+    }
+
+    pub fn get(&self, blueprint: Blueprint) -> Option<&Recipe> {
+        self.recipes.iter().find(|r| r.blueprint == blueprint)
+    }
+
+    pub fn ids(&self) -> Vec<[u8; 32]> {
+        self.recipes.iter().map(Recipe::id).collect()
+    }
+
+    pub fn hash(&self) -> [u8; 32] {
+        recipe_table_hash(&self.ids())
+    }
+}
+
+/// `SHA-256(RECIPE_TABLE_DOMAIN || ids)`, which identifies a recipe table by the ids of its
+/// recipes, in order.
+pub fn recipe_table_hash(ids: &[[u8; 32]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(RECIPE_TABLE_DOMAIN);
+    for id in ids {
+        hasher.update(id);
+    }
+    hasher.finalize().into()
+}
+
+/// Asserts that the verified child objects are exactly the objects listed in `inputs`, in any
+/// order, and that their blueprints, each counted as many times as its quantity, make up `times`
+/// copies of the `required` multiset.
+pub fn match_inputs(
+    required: &[Blueprint],
+    times: u64,
+    inputs: &[ObjectHash],
+    verified: &[ObjectOutput],
+) {
+    match_objects("inputs", required, times, inputs, verified);
+}
+
+/// Same as [`match_inputs`] for the catalysts of a recipe, which are referenced but not consumed,
+/// and so are required once whatever the quantity crafted.
+pub fn match_catalysts(
+    required: &[Blueprint],
+    catalysts: &[ObjectHash],
+    verified: &[ObjectOutput],
+) {
+    match_objects("catalysts", required, 1, catalysts, verified);
+}
+
+fn match_objects(
+    kind: &str,
+    required: &[Blueprint],
+    times: u64,
+    hashes: &[ObjectHash],
+    verified: &[ObjectOutput],
+) {
+    assert!(
+        verified.len() == hashes.len(),
+        "Must verify one object per entry of {}",
+        kind
+    );
+
+    let mut required_counts: BTreeMap<Blueprint, u64> = BTreeMap::new();
+    for blueprint in required {
+        *required_counts.entry(*blueprint).or_default() += times;
+    }
+    let mut verified_counts: BTreeMap<Blueprint, u64> = BTreeMap::new();
+    for object in verified {
+        *verified_counts.entry(object.blueprint).or_default() += object.quantity as u64;
+    }
+    assert!(
+        verified_counts == required_counts,
+        "Blueprints of {} must match the recipe",
+        kind
+    );
+
+    let mut expected_hashes: Vec<&ObjectHash> = hashes.iter().collect();
+    let mut verified_hashes: Vec<&ObjectHash> = verified.iter().map(|o| &o.hash).collect();
+    expected_hashes.sort();
+    verified_hashes.sort();
+    assert!(
+        expected_hashes.windows(2).all(|w| w[0] != w[1]),
+        "Entries of {} must be distinct",
+        kind
+    );
+    assert!(
+        verified_hashes == expected_hashes,
+        "Verified objects must match the object {}",
+        kind
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OwnerKey, Rarity};
+
+    use super::*;
+
+    const AXE_LIKE: [Blueprint; 3] = [Blueprint::Wood, Blueprint::Wood, Blueprint::Stone];
+
+    fn object(id: u8, blueprint: Blueprint, quantity: u32) -> ObjectOutput {
+        ObjectOutput {
+            hash: ObjectHash([id; 32]),
+            owner: OwnerKey([2; 33]),
+            blueprint,
+            quantity,
+            durability: None,
+            rarity: Rarity::Common,
+            anchor: None,
+            recipe: [0; 32],
+            recipe_table: [0; 32],
+            program: [0; 8],
+            consumed: vec![],
+            referenced: vec![],
+            worn: vec![],
+        }
+    }
+
+    #[test]
+    fn recipe_without_durability_differs_from_zero_durability() {
+        let recipe = Blueprint::Wood.recipe();
+        let worn_out = Recipe {
+            durability: Some(0),
+            ..recipe.clone()
+        };
+        assert_eq!(recipe.durability, None);
+        assert_ne!(recipe.canonical_bytes(), worn_out.canonical_bytes());
+        assert_ne!(recipe.id(), worn_out.id());
+    }
+
+    fn hashes(objects: &[ObjectOutput]) -> Vec<ObjectHash> {
+        objects.iter().map(|object| object.hash).collect()
+    }
+
+    /// Every ordering of `objects`.
+    fn permutations(objects: &[ObjectOutput]) -> Vec<Vec<ObjectOutput>> {
+        if objects.is_empty() {
+            return vec![vec![]];
+        }
+        (0..objects.len())
+            .flat_map(|i| {
+                let mut rest = objects.to_vec();
+                let first = rest.remove(i);
+                permutations(&rest).into_iter().map(move |mut tail| {
+                    tail.insert(0, first.clone());
+                    tail
+                })
+            })
+            .collect()
+    }
+
+    fn woods_and_stone() -> Vec<ObjectOutput> {
+        vec![
+            object(1, Blueprint::Wood, 1),
+            object(2, Blueprint::Wood, 1),
+            object(3, Blueprint::Stone, 1),
+        ]
+    }
+
+    #[test]
+    fn matches_inputs_in_any_order() {
+        let orders = permutations(&woods_and_stone());
+        assert_eq!(orders.len(), 6);
+        for inputs in &orders {
+            for verified in &orders {
+                match_inputs(&AXE_LIKE, 1, &hashes(inputs), verified);
+            }
+        }
+    }
+
+    #[test]
+    fn matches_stacks_by_quantity() {
+        let verified = [
+            object(1, Blueprint::Wood, 4),
+            object(3, Blueprint::Stone, 2),
+        ];
+        match_inputs(&AXE_LIKE, 2, &hashes(&verified), &verified);
+    }
+
+    #[test]
+    fn matches_catalysts_once() {
+        let verified = [object(4, Blueprint::Axe, 1)];
+        match_catalysts(&[Blueprint::Axe], &hashes(&verified), &verified);
+    }
+
+    #[test]
+    #[should_panic(expected = "Blueprints of inputs must match the recipe")]
+    fn rejects_a_wrong_multiset() {
+        let verified = [
+            object(1, Blueprint::Wood, 1),
+            object(2, Blueprint::Stone, 1),
+            object(3, Blueprint::Stone, 1),
+        ];
+        match_inputs(&AXE_LIKE, 1, &hashes(&verified), &verified);
+    }
+
+    #[test]
+    #[should_panic(expected = "Blueprints of inputs must match the recipe")]
+    fn rejects_a_missing_copy() {
+        let verified = woods_and_stone();
+        match_inputs(&AXE_LIKE, 2, &hashes(&verified), &verified);
+    }
+
+    #[test]
+    #[should_panic(expected = "Entries of inputs must be distinct")]
+    fn rejects_the_same_object_twice() {
+        let wood = object(1, Blueprint::Wood, 1);
+        let verified = [wood.clone(), wood, object(3, Blueprint::Stone, 1)];
+        match_inputs(&AXE_LIKE, 1, &hashes(&verified), &verified);
+    }
+
+    #[test]
+    #[should_panic(expected = "Must verify one object per entry of inputs")]
+    fn rejects_fewer_verified_objects_than_inputs() {
+        let objects = woods_and_stone();
+        match_inputs(&AXE_LIKE, 1, &hashes(&objects), &objects[..2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Must verify one object per entry of catalysts")]
+    fn rejects_more_verified_objects_than_catalysts() {
+        let verified = [object(4, Blueprint::Axe, 1), object(5, Blueprint::Axe, 1)];
+        match_catalysts(&[Blueprint::Axe], &hashes(&verified[..1]), &verified);
+    }
+
+    #[test]
+    #[should_panic(expected = "Verified objects must match the object inputs")]
+    fn rejects_verified_objects_not_listed() {
+        let verified = woods_and_stone();
+        let mut inputs = hashes(&verified);
+        inputs[0] = ObjectHash([9; 32]);
+        match_inputs(&AXE_LIKE, 1, &inputs, &verified);
+    }
+}
@@ -0,0 +1,372 @@
+//! Global object state as a sparse Merkle tree of depth 256, keyed by object hash. Private
+//! objects are keyed by commitments and nullifiers instead, see `commit_program`.
+//!
+//! Empty subtrees hash to zero at every depth, so a tree only costs hashes along the paths of its
+//! non-empty leaves. Proofs only carry the siblings of a path that aren't empty.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{ObjectHash, OwnerKey};
+
+const LEAF_DOMAIN: &[u8] = b"digital-objects/state-leaf";
+const NODE_DOMAIN: &[u8] = b"digital-objects/state-node";
+const DEPTH: usize = 256;
+
+/// Root of the state before any commitment.
+pub const EMPTY_ROOT: [u8; 32] = [0; 32];
+
+/// State of an object in the tree.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub enum Leaf {
+    /// The object was never created.
+    #[default]
+    Empty,
+    /// The object was created and is held by its current owner.
+    Live(OwnerKey),
+    /// The object was consumed, and can't be created again.
+    Consumed,
+    /// Commitment to a private object, which can be consumed with the secret behind the tag.
+    Committed(#[serde(with = "crate::bytes")] [u8; 32]),
+    /// Nullifier of a consumed private object, or key of a committed swap.
+    Nullifier,
+    /// The tool was worn by use, and replaced by its successor.
+    Worn(ObjectHash),
+    /// The object was burned by its owner.
+    Burned,
+    /// The tool wore out by use, leaving no successor.
+    WornOut,
+}
+
+/// Change of the leaf at `key` from `old` to `new`. Reads are updates where both are equal.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct StateUpdate {
+    pub key: ObjectHash,
+    pub old: Leaf,
+    pub new: Leaf,
+}
+
+/// Merkle proof of the leaf at a key.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct StateProof {
+    /// Bit `d` (big-endian) is set if the sibling of the path at depth `d` is non-empty.
+    #[serde(with = "crate::bytes")]
+    non_empty: [u8; 32],
+    /// Non-empty siblings, from the root down.
+    siblings: Vec<[u8; 32]>,
+}
+
+/// Full state tree, kept by the synchronizer and used by committers to generate proofs.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct StateTree {
+    leaves: BTreeMap<ObjectHash, Leaf>,
+}
+
+impl Leaf {
+    fn hash(&self, key: &ObjectHash) -> [u8; 32] {
+        let (kind, data): (u8, &[u8]) = match self {
+            Leaf::Empty => return EMPTY_ROOT,
+            Leaf::Live(owner) => (1, &owner.0),
+            Leaf::Consumed => (2, &[]),
+            Leaf::Committed(tag) => (3, tag),
+            Leaf::Nullifier => (4, &[]),
+            Leaf::Worn(successor) => (5, &successor.0),
+            Leaf::Burned => (6, &[]),
+            Leaf::WornOut => (7, &[]),
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(LEAF_DOMAIN);
+        hasher.update(key.0);
+        hasher.update([kind]);
+        hasher.update(data);
+        hasher.finalize().into()
+    }
+}
+
+impl StateProof {
+    /// Root of the tree with `leaf` at `key` and this proof's siblings.
+    fn root(&self, key: &ObjectHash, leaf: &Leaf) -> [u8; 32] {
+        let mut siblings = self.siblings.iter().rev();
+        let mut cur = leaf.hash(key);
+        for depth in (0..DEPTH).rev() {
+            let sibling = if bit(&self.non_empty, depth) {
+                *siblings.next().expect("Missing sibling in state proof")
+            } else {
+                EMPTY_ROOT
+            };
+            cur = if bit(&key.0, depth) {
+                node(&sibling, &cur)
+            } else {
+                node(&cur, &sibling)
+            };
+        }
+        assert!(siblings.next().is_none(), "Extra siblings in state proof");
+        cur
+    }
+
+    /// Checks that `update.old` is the leaf at `update.key` under `root`, and returns the root
+    /// after the update.
+    pub fn update(&self, root: &[u8; 32], update: &StateUpdate) -> [u8; 32] {
+        assert!(
+            self.root(&update.key, &update.old) == *root,
+            "Object {} is not {:?}",
+            update.key,
+            update.old
+        );
+        self.root(&update.key, &update.new)
+    }
+}
+
+impl StateTree {
+    pub fn get(&self, key: &ObjectHash) -> Leaf {
+        self.leaves.get(key).copied().unwrap_or_default()
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        let leaves: Vec<_> = self.leaves.iter().collect();
+        subtree_root(&leaves, 0)
+    }
+
+    pub fn prove(&self, key: &ObjectHash) -> StateProof {
+        let leaves: Vec<_> = self.leaves.iter().collect();
+        let mut path = &leaves[..];
+        let mut proof = StateProof {
+            non_empty: [0; 32],
+            siblings: Vec::new(),
+        };
+        for depth in 0..DEPTH {
+            let (left, right) = path.split_at(path.partition_point(|(k, _)| !bit(&k.0, depth)));
+            let (next, other) = if bit(&key.0, depth) {
+                (right, left)
+            } else {
+                (left, right)
+            };
+            let sibling = subtree_root(other, depth + 1);
+            if sibling != EMPTY_ROOT {
+                proof.non_empty[depth / 8] |= 0x80 >> (depth % 8);
+                proof.siblings.push(sibling);
+            }
+            path = next;
+        }
+        proof
+    }
+
+    /// Applies `updates` in order, returning the proof of each against the root before it.
+    pub fn apply(&mut self, updates: &[StateUpdate]) -> Vec<StateProof> {
+        updates
+            .iter()
+            .map(|update| {
+                assert!(
+                    self.get(&update.key) == update.old,
+                    "Object {} is not {:?}",
+                    update.key,
+                    update.old
+                );
+                let proof = self.prove(&update.key);
+                if update.new == Leaf::Empty {
+                    self.leaves.remove(&update.key);
+                } else {
+                    self.leaves.insert(update.key, update.new);
+                }
+                proof
+            })
+            .collect()
+    }
+}
+
+/// Root of the subtree at `depth` holding `leaves`, sorted by key and sharing their first
+/// `depth` bits.
+fn subtree_root(leaves: &[(&ObjectHash, &Leaf)], depth: usize) -> [u8; 32] {
+    match leaves {
+        [] => EMPTY_ROOT,
+        [(key, leaf)] if depth == DEPTH => leaf.hash(key),
+        _ => {
+            let (left, right) = leaves.split_at(leaves.partition_point(|(k, _)| !bit(&k.0, depth)));
+            node(
+                &subtree_root(left, depth + 1),
+                &subtree_root(right, depth + 1),
+            )
+        }
+    }
+}
+
+fn node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    if *left == EMPTY_ROOT && *right == EMPTY_ROOT {
+        return EMPTY_ROOT;
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(NODE_DOMAIN);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Bit `i` of `bytes`, most significant first.
+fn bit(bytes: &[u8; 32], i: usize) -> bool {
+    (bytes[i / 8] >> (7 - i % 8)) & 1 == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    fn key(bytes: &[(usize, u8)]) -> ObjectHash {
+        let mut key = [0u8; 32];
+        for &(i, byte) in bytes {
+            key[i] = byte;
+        }
+        ObjectHash(key)
+    }
+
+    fn owner(byte: u8) -> OwnerKey {
+        OwnerKey([byte; 33])
+    }
+
+    fn insert(tree: &mut StateTree, key: ObjectHash, leaf: Leaf) {
+        tree.apply(&[StateUpdate {
+            key,
+            old: tree.get(&key),
+            new: leaf,
+        }]);
+    }
+
+    /// Tree with leaves spread over both halves, and two keys differing only in their last bit.
+    fn tree() -> StateTree {
+        let mut tree = StateTree::default();
+        insert(&mut tree, key(&[(0, 0x80)]), Leaf::Live(owner(1)));
+        insert(&mut tree, key(&[(0, 0x01)]), Leaf::Consumed);
+        insert(&mut tree, key(&[(31, 0x02)]), Leaf::Committed([7; 32]));
+        insert(&mut tree, key(&[(31, 0x03)]), Leaf::Nullifier);
+        tree
+    }
+
+    #[test]
+    fn leaves_hash_by_kind() {
+        let leaves = [
+            Leaf::Live(owner(1)),
+            Leaf::Consumed,
+            Leaf::Committed([0; 32]),
+            Leaf::Nullifier,
+            Leaf::Worn(ObjectHash([0; 32])),
+            Leaf::Burned,
+            Leaf::WornOut,
+        ];
+        let key = key(&[(0, 0x01)]);
+        let hashes: BTreeSet<_> = leaves.iter().map(|leaf| leaf.hash(&key)).collect();
+        assert_eq!(hashes.len(), leaves.len());
+        assert!(!hashes.contains(&EMPTY_ROOT));
+    }
+
+    #[test]
+    fn empty_tree_has_empty_root() {
+        assert_eq!(StateTree::default().root(), EMPTY_ROOT);
+    }
+
+    #[test]
+    fn root_is_independent_of_insertion_order() {
+        let mut reversed = StateTree::default();
+        let leaves: Vec<_> = tree().leaves.into_iter().collect();
+        for (key, leaf) in leaves.into_iter().rev() {
+            insert(&mut reversed, key, leaf);
+        }
+        assert_eq!(reversed.root(), tree().root());
+    }
+
+    #[test]
+    fn removing_every_leaf_empties_the_tree() {
+        let mut tree = tree();
+        let keys: Vec<_> = tree.leaves.keys().copied().collect();
+        for key in keys {
+            insert(&mut tree, key, Leaf::Empty);
+        }
+        assert_eq!(tree.root(), EMPTY_ROOT);
+    }
+
+    #[test]
+    fn proves_membership() {
+        let tree = tree();
+        let root = tree.root();
+        for (key, leaf) in &tree.leaves {
+            let read = StateUpdate {
+                key: *key,
+                old: *leaf,
+                new: *leaf,
+            };
+            assert_eq!(tree.prove(key).update(&root, &read), root);
+        }
+    }
+
+    #[test]
+    fn proves_non_membership() {
+        let tree = tree();
+        let root = tree.root();
+        for key in [key(&[]), key(&[(31, 0x01)]), key(&[(0, 0xff)])] {
+            let read = StateUpdate {
+                key,
+                old: Leaf::Empty,
+                new: Leaf::Empty,
+            };
+            assert_eq!(tree.prove(&key).update(&root, &read), root);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "is not Empty")]
+    fn rejects_non_membership_of_a_leaf() {
+        let tree = tree();
+        let key = key(&[(31, 0x02)]);
+        let read = StateUpdate {
+            key,
+            old: Leaf::Empty,
+            new: Leaf::Empty,
+        };
+        tree.prove(&key).update(&tree.root(), &read);
+    }
+
+    #[test]
+    #[should_panic(expected = "is not Live")]
+    fn rejects_a_wrong_old_leaf() {
+        let tree = tree();
+        let key = key(&[(0, 0x80)]);
+        let update = StateUpdate {
+            key,
+            old: Leaf::Live(owner(2)),
+            new: Leaf::Consumed,
+        };
+        tree.prove(&key).update(&tree.root(), &update);
+    }
+
+    #[test]
+    fn update_matches_the_updated_tree() {
+        let mut tree = tree();
+        let updates = [
+            StateUpdate {
+                key: key(&[(0, 0x80)]),
+                old: Leaf::Live(owner(1)),
+                new: Leaf::Live(owner(2)),
+            },
+            StateUpdate {
+                key: key(&[(31, 0x01)]),
+                old: Leaf::Empty,
+                new: Leaf::Live(owner(3)),
+            },
+            StateUpdate {
+                key: key(&[(31, 0x03)]),
+                old: Leaf::Nullifier,
+                new: Leaf::Empty,
+            },
+        ];
+        let root = tree.root();
+        let proofs = tree.apply(&updates);
+        let updated = updates
+            .iter()
+            .zip(proofs.iter())
+            .fold(root, |root, (update, proof)| proof.update(&root, update));
+        assert_eq!(updated, tree.root());
+        assert_eq!(tree.get(&key(&[(31, 0x01)])), Leaf::Live(owner(3)));
+    }
+}
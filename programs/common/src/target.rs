@@ -0,0 +1,194 @@
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ObjectHash;
+
+/// Inclusive upper bound on object hashes, compared as 256-bit big-endian integers.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Target(#[serde(with = "crate::bytes")] pub [u8; 32]);
+
+impl Target {
+    /// Target met by every hash.
+    pub const MAX: Target = Target([0xff; 32]);
+
+    /// Target met by hashes with at least `bits` leading zero bits.
+    pub const fn from_leading_zeros(bits: u32) -> Self {
+        let mut bytes = [0xffu8; 32];
+        let mut i = 0;
+        while i < 32 {
+            let first_bit = i as u32 * 8;
+            if bits >= first_bit + 8 {
+                bytes[i] = 0;
+            } else if bits > first_bit {
+                bytes[i] = 0xff >> (bits - first_bit);
+            }
+            i += 1;
+        }
+        Self(bytes)
+    }
+
+    /// Target from its compact form: the top byte is the length `e` of the target in bytes and
+    /// the low three bytes are its unsigned mantissa `m`, so the target is `m * 256^(e - 3)`.
+    pub const fn from_compact(compact: u32) -> Self {
+        let exponent = (compact >> 24) as usize;
+        assert!(exponent <= 32, "compact target exponent out of range");
+        let mut bytes = [0u8; 32];
+        let mut k = 0;
+        while k < 3 {
+            let pos = 32 + k;
+            if pos >= exponent && pos - exponent < 32 {
+                bytes[pos - exponent] = (compact >> (8 * (2 - k))) as u8;
+            }
+            k += 1;
+        }
+        Self(bytes)
+    }
+
+    /// Compact form of the target, truncated to the three most significant bytes.
+    pub fn to_compact(&self) -> u32 {
+        let Some(first) = self.0.iter().position(|byte| *byte != 0) else {
+            return 0;
+        };
+        let mut mantissa = 0u32;
+        for k in 0..3 {
+            mantissa = (mantissa << 8) | *self.0.get(first + k).unwrap_or(&0) as u32;
+        }
+        ((32 - first as u32) << 24) | mantissa
+    }
+
+    /// Target for mining a stack of `quantity` objects at once, which takes as many hashes as
+    /// mining them one by one.
+    pub fn for_quantity(&self, quantity: u32) -> Self {
+        assert!(quantity > 0, "Quantity must be positive");
+        let mut bytes = [0u8; 32];
+        let mut rem = 0u64;
+        for (byte, out) in self.0.iter().zip(bytes.iter_mut()) {
+            let cur = (rem << 8) | *byte as u64;
+            *out = (cur / quantity as u64) as u8;
+            rem = cur % quantity as u64;
+        }
+        Self(bytes)
+    }
+
+    pub fn is_met_by(&self, hash: &ObjectHash) -> bool {
+        hash.0 <= self.0
+    }
+
+    /// Expected number of hashes to try before finding one that meets the target.
+    pub fn expected_hashes(&self) -> f64 {
+        let target = self
+            .0
+            .iter()
+            .fold(0f64, |acc, byte| acc * 256.0 + *byte as f64);
+        2f64.powi(256) / (target + 1.0)
+    }
+
+    /// Expected time to meet the target when computing `hash_rate` hashes per second.
+    pub fn expected_time(&self, hash_rate: f64) -> Duration {
+        Duration::from_secs_f64(self.expected_hashes() / hash_rate)
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl fmt::Debug for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Target with `bytes` as its least significant bytes.
+    fn low(bytes: &[u8]) -> Target {
+        let mut target = [0u8; 32];
+        target[32 - bytes.len()..].copy_from_slice(bytes);
+        Target(target)
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= expected * 1e-9,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn from_leading_zeros() {
+        assert_eq!(Target::from_leading_zeros(0), Target::MAX);
+        assert_eq!(Target::from_leading_zeros(256), Target([0; 32]));
+
+        let target = Target::from_leading_zeros(12);
+        assert_eq!(target.0[..3], [0x00, 0x0f, 0xff]);
+        assert!(target.0[3..].iter().all(|byte| *byte == 0xff));
+
+        let mut hash = [0xff; 32];
+        hash[..2].copy_from_slice(&[0x00, 0x0f]);
+        assert!(target.is_met_by(&ObjectHash(hash)));
+        hash[1] = 0x10;
+        assert!(!target.is_met_by(&ObjectHash(hash)));
+    }
+
+    #[test]
+    fn compact_round_trip() {
+        for compact in [0x0101_0000, 0x1e0f_ffff, 0x2012_3456, 0x0212_3400] {
+            assert_eq!(Target::from_compact(compact).to_compact(), compact);
+        }
+        for target in [
+            Target::from_leading_zeros(248),
+            Target::from_leading_zeros(255),
+            low(&[0x12, 0x34]),
+            Target([0; 32]),
+        ] {
+            assert_eq!(Target::from_compact(target.to_compact()), target);
+        }
+    }
+
+    #[test]
+    fn compact_truncates_to_three_bytes() {
+        assert_eq!(Target::MAX.to_compact(), 0x20ff_ffff);
+        assert_eq!(
+            Target::from_compact(Target::MAX.to_compact()),
+            Target::from_compact(0x20ff_ffff)
+        );
+        assert_eq!(Target::from_compact(0x20ff_ffff).0[3..], [0; 29]);
+    }
+
+    #[test]
+    fn for_quantity() {
+        let target = Target::from_leading_zeros(8);
+        assert_eq!(target.for_quantity(1), target);
+        assert_eq!(Target::MAX.for_quantity(2), Target::from_leading_zeros(1));
+        assert_eq!(target.for_quantity(256), Target::from_leading_zeros(16));
+        assert_eq!(low(&[0x01, 0x00]).for_quantity(3), low(&[0x55]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Quantity must be positive")]
+    fn for_quantity_rejects_zero() {
+        Target::MAX.for_quantity(0);
+    }
+
+    #[test]
+    fn expected_hashes() {
+        assert_close(Target::MAX.expected_hashes(), 1.0);
+        assert_close(Target::from_leading_zeros(20).expected_hashes(), 1048576.0);
+        assert_close(low(&[0x01]).expected_hashes(), 2f64.powi(255));
+    }
+
+    #[test]
+    fn expected_time() {
+        let target = Target::from_leading_zeros(20);
+        assert_close(target.expected_time(1048576.0).as_secs_f64(), 1.0);
+        assert_close(target.expected_time(1024.0).as_secs_f64(), 1024.0);
+    }
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Hash function iterated by the pow program.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum PowFunction {
+    /// `n_iters` chained SHA-256 hashes.
+    Sha256 = 1,
+    /// `n_iters` chained Keccak-256 hashes.
+    Keccak256 = 2,
+    /// Sequential memory-hard function (scrypt's ROMix over SHA-256), which fills `n_iters`
+    /// 32-byte blocks and then reads `n_iters` of them back in a data-dependent order.
+    MemoryHard = 3,
+}
+
+/// Proof of work required on top of mining to craft an object, see `pow_program`.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Work {
+    pub function: PowFunction,
+    pub n_iters: u32,
+}
+
+impl Work {
+    /// Work required to craft a stack of `quantity` objects at once.
+    pub fn for_quantity(self, quantity: u32) -> Self {
+        Self {
+            function: self.function,
+            n_iters: self
+                .n_iters
+                .checked_mul(quantity)
+                .expect("Work iterations overflow"),
+        }
+    }
+}
+
+impl fmt::Display for PowFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PowFunction::Sha256 => "sha256",
+            PowFunction::Keccak256 => "keccak256",
+            PowFunction::MemoryHard => "memory-hard",
+        })
+    }
+}
+
+impl fmt::Display for Work {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} x{}", self.function, self.n_iters)
+    }
+}
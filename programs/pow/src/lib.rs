@@ -1,14 +1,205 @@
+use common::{PowFunction, Work};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct PowIn {
+    pub function: PowFunction,
     pub n_iters: u32,
-    pub input: String,
+    pub input: [u8; 32],
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct PowOut {
+    pub function: PowFunction,
     pub n_iters: u32,
-    pub input: String,
-    pub output: String,
+    pub input: [u8; 32],
+    pub output: [u8; 32],
+}
+
+impl PowIn {
+    pub fn new(work: Work, input: [u8; 32]) -> Self {
+        Self {
+            function: work.function,
+            n_iters: work.n_iters,
+            input,
+        }
+    }
+
+    /// Computes the proof of work.
+    pub fn run(&self) -> PowOut {
+        let output = match self.function {
+            PowFunction::Sha256 => iterate::<Sha256>(self.n_iters, self.input),
+            PowFunction::Keccak256 => iterate::<Keccak256>(self.n_iters, self.input),
+            PowFunction::MemoryHard => memory_hard(self.n_iters, self.input),
+        };
+        PowOut {
+            function: self.function,
+            n_iters: self.n_iters,
+            input: self.input,
+            output,
+        }
+    }
+}
+
+impl PowOut {
+    /// Whether this is the output of `work` on `input`.
+    pub fn is_work(&self, work: Work, input: [u8; 32]) -> bool {
+        self.function == work.function && self.n_iters == work.n_iters && self.input == input
+    }
+}
+
+fn iterate<D: Digest>(n_iters: u32, input: [u8; 32]) -> [u8; 32] {
+    let mut cur = input;
+    for _ in 0..n_iters {
+        let next = D::digest(cur);
+        cur.copy_from_slice(&next);
+    }
+    cur
+}
+
+/// scrypt's ROMix with SHA-256 as the mixing function.
+fn memory_hard(n_iters: u32, input: [u8; 32]) -> [u8; 32] {
+    let n = n_iters as usize;
+    let mut memory = Vec::with_capacity(n);
+    let mut cur = input;
+    for _ in 0..n {
+        memory.push(cur);
+        cur = Sha256::digest(cur).into();
+    }
+    for _ in 0..n {
+        let j = u32::from_le_bytes([cur[0], cur[1], cur[2], cur[3]]) as usize % n;
+        for (c, m) in cur.iter_mut().zip(memory[j].iter()) {
+            *c ^= m;
+        }
+        cur = Sha256::digest(cur).into();
+    }
+    cur
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: [u8; 32] = [7; 32];
+
+    fn run(function: PowFunction, n_iters: u32) -> String {
+        let out = PowIn::new(Work { function, n_iters }, INPUT).run();
+        assert!(out.is_work(Work { function, n_iters }, INPUT));
+        hex::encode(out.output)
+    }
+
+    #[test]
+    fn no_iterations_return_the_input() {
+        for function in [
+            PowFunction::Sha256,
+            PowFunction::Keccak256,
+            PowFunction::MemoryHard,
+        ] {
+            assert_eq!(run(function, 0), hex::encode(INPUT));
+        }
+    }
+
+    #[test]
+    fn sha256_matches_test_vectors() {
+        assert_eq!(
+            run(PowFunction::Sha256, 1),
+            "4bb06f8e4e3a7715d201d573d0aa423762e55dabd61a2c02278fa56cc6d294e0"
+        );
+        assert_eq!(
+            run(PowFunction::Sha256, 2),
+            "eb60bdee05596734335a93786236c2df6642b9f2730ff30e5242e6d4c1f3fec1"
+        );
+        assert_eq!(
+            run(PowFunction::Sha256, 1000),
+            "4436ff1b2ba088f196f3f3606c6d0b45d7b9a484f8d5a2001c8bd35dd4500e91"
+        );
+    }
+
+    #[test]
+    fn keccak256_matches_test_vectors() {
+        assert_eq!(
+            run(PowFunction::Keccak256, 1),
+            "7b062064095a97578a0f0cf535dd321f48f102ebc87a8ca16dd4b4c5fc6c4da8"
+        );
+        assert_eq!(
+            run(PowFunction::Keccak256, 2),
+            "c952fd2a927b2951d407ed627c0a6b64741699fa0289fb17a6f5e3a296e9ba52"
+        );
+        assert_eq!(
+            run(PowFunction::Keccak256, 1000),
+            "94857ad80a17dfa60fdabbbecaae392cde18404e9ad7f982ab905d5eaa796505"
+        );
+    }
+
+    #[test]
+    fn keccak256_is_not_sha3() {
+        assert_ne!(
+            hex::encode(iterate::<Keccak256>(1, [0; 32])),
+            hex::encode(sha3::Sha3_256::digest([0; 32]))
+        );
+    }
+
+    #[test]
+    fn memory_hard_matches_test_vectors() {
+        assert_eq!(
+            run(PowFunction::MemoryHard, 1),
+            "8ba59ae3abd731385f0157b673aa5bfecb9dfcd327a61bdac16f5e0d2d1149da"
+        );
+        assert_eq!(
+            run(PowFunction::MemoryHard, 2),
+            "deae9656f02e1243952729455e5102176d0b6d3a51c438db56208af1f27bfeea"
+        );
+        assert_eq!(
+            run(PowFunction::MemoryHard, 3),
+            "25753a52a707e6f0801c8e4a5cd3565c5ae787be85bbac45785ab45bb36057e8"
+        );
+        assert_eq!(
+            run(PowFunction::MemoryHard, 64),
+            "4758a68de9e9cb0ef94866ddb569dc02cd8c33175effdcdaf4bea318d00cfc01"
+        );
+    }
+
+    #[test]
+    fn memory_hard_with_one_block_mixes_the_input() {
+        // With a single block, every index wraps to the input itself.
+        let mut mixed: [u8; 32] = Sha256::digest(INPUT).into();
+        for (c, m) in mixed.iter_mut().zip(INPUT.iter()) {
+            *c ^= m;
+        }
+        assert_eq!(
+            memory_hard(1, INPUT),
+            <[u8; 32]>::from(Sha256::digest(mixed))
+        );
+    }
+
+    #[test]
+    fn memory_hard_differs_from_iterated_sha256() {
+        for n_iters in 1..8 {
+            assert_ne!(
+                memory_hard(n_iters, INPUT),
+                iterate::<Sha256>(2 * n_iters, INPUT)
+            );
+        }
+    }
+
+    #[test]
+    fn is_work_checks_every_field() {
+        let work = Work {
+            function: PowFunction::Sha256,
+            n_iters: 2,
+        };
+        let out = PowIn::new(work, INPUT).run();
+        assert!(out.is_work(work, INPUT));
+        assert!(!out.is_work(work, [8; 32]));
+        assert!(!out.is_work(Work { n_iters: 3, ..work }, INPUT));
+        assert!(!out.is_work(
+            Work {
+                function: PowFunction::Keccak256,
+                ..work
+            },
+            INPUT
+        ));
+    }
 }
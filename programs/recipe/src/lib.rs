@@ -0,0 +1,28 @@
+use common::{ObjectInput, Recipe};
+use serde::{Deserialize, Serialize};
+
+/// Vkey digests of the programs whose proofs this program verifies, generated by `build.rs`.
+pub mod vkeys {
+    include!(concat!(env!("OUT_DIR"), "/vkeys.rs"));
+}
+
+/// How the object came to be.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum Derivation {
+    /// Crafted with the recipe of its blueprint.
+    Craft(Recipe),
+    /// Stack split or merged from its inputs by the commit program.
+    Restack,
+    /// Successor of the tool it takes as input, worn by the commit program.
+    Wear,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RecipeIn {
+    pub object: ObjectInput,
+    pub derivation: Derivation,
+    /// Ids of the recipes in the table, which must include the recipe the object is crafted with.
+    pub recipe_table: Vec<[u8; 32]>,
+    /// Vkey digest of this program, which also proved the inputs and catalysts.
+    pub program: [u32; 8],
+}
@@ -0,0 +1,194 @@
+//! A program that crafts an object of any blueprint by following its recipe, restacks objects, or
+//! wears a tool
+
+// These two lines are necessary for the program to properly compile.
+//
+// Under the hood, we wrap your main function with some extra code so that it behaves properly
+// inside the zkVM.
+#![no_main]
+
+use common::{
+    guest::read_verified, match_catalysts, match_inputs, recipe_table_hash, ObjectHash,
+    ObjectOutput, Rarity, Recipe, RESTACKED_RECIPE, WORN_RECIPE,
+};
+use recipe_program::{vkeys::POW_VKEY_DIGEST, Derivation, RecipeIn};
+
+sp1_zkvm::entrypoint!(main);
+
+/// Reads `n` objects proved by the recipe program with vkey digest `program`.
+fn read_verified_objects(n: usize, program: &[u32; 8]) -> Vec<ObjectOutput> {
+    (0..n)
+        .map(|_| {
+            let object = read_verified::<ObjectOutput>(program);
+            assert!(
+                object.program == *program,
+                "Object {} must be proved by the recipe program",
+                object.hash
+            );
+            object
+        })
+        .collect()
+}
+
+pub fn main() {
+    // Read an input to the program.
+    //
+    // Behind the scenes, this compiles down to a system call which handles reading inputs
+    // from the prover.
+    let inp = sp1_zkvm::io::read::<RecipeIn>();
+    let object = &inp.object.object;
+    assert!(object.quantity > 0, "Quantity must be positive");
+    // No block has a zero root, which would only stand for a missing anchor.
+    assert!(
+        object.anchor != Some([0; 32]),
+        "Anchor must be a beacon block root"
+    );
+    let object_hash = object.hash();
+
+    let (recipe_id, rarity, catalysts) = match &inp.derivation {
+        Derivation::Craft(recipe) => {
+            let catalysts = craft(&inp, recipe, &object_hash);
+            // Like mining, the tier of a stack is relative to the stack's target.
+            let target = recipe.target.for_quantity(object.quantity);
+            (recipe.id(), Rarity::of(&target, &object_hash), catalysts)
+        }
+        Derivation::Restack => {
+            restack(&inp);
+            (RESTACKED_RECIPE, Rarity::Common, vec![])
+        }
+        Derivation::Wear => (WORN_RECIPE, wear(&inp), vec![]),
+    };
+    // Tools are worn by their uses when the object is committed, other catalysts are referenced.
+    let (worn, referenced): (Vec<&ObjectOutput>, Vec<&ObjectOutput>) = catalysts
+        .iter()
+        .partition(|catalyst| catalyst.durability.is_some());
+
+    // Write the output of the program.
+    //
+    // Behind the scenes, this also compiles down to a system call which handles writing
+    // outputs to the prover.
+    sp1_zkvm::io::commit(&ObjectOutput {
+        hash: object_hash,
+        owner: object.owner,
+        blueprint: object.blueprint,
+        quantity: object.quantity,
+        durability: object.durability,
+        rarity,
+        anchor: object.anchor,
+        recipe: recipe_id,
+        recipe_table: recipe_table_hash(&inp.recipe_table),
+        program: inp.program,
+        consumed: object.inputs.clone(),
+        referenced: referenced.iter().map(|catalyst| catalyst.hash).collect(),
+        worn: worn.iter().map(|catalyst| catalyst.hash).collect(),
+    });
+}
+
+/// Checks that the object is a stack of `object.quantity` objects crafted with `recipe`, and
+/// returns its verified catalysts.
+fn craft(inp: &RecipeIn, recipe: &Recipe, object_hash: &ObjectHash) -> Vec<ObjectOutput> {
+    let object = &inp.object.object;
+    assert!(
+        inp.recipe_table.contains(&recipe.id()),
+        "Recipe must be in the recipe table"
+    );
+    assert!(
+        object.blueprint == recipe.blueprint,
+        "Blueprint must be {}",
+        recipe.blueprint
+    );
+    assert!(
+        object.durability == recipe.durability && object.durability != Some(0),
+        "Durability must be that of the recipe, and positive"
+    );
+    assert!(
+        recipe.durability.is_none() || object.quantity == 1,
+        "Tools can't be stacked"
+    );
+
+    // A stack takes as much mining and work as its objects one by one.
+    assert!(
+        recipe
+            .target
+            .for_quantity(object.quantity)
+            .is_met_by(object_hash),
+        "Object hash does not meet mining difficulty"
+    );
+
+    match recipe.work {
+        Some(work) => {
+            let work = work.for_quantity(object.quantity);
+            let pow_public_values = read_verified::<pow_program::PowOut>(&POW_VKEY_DIGEST);
+            assert!(
+                pow_public_values.is_work(work, object_hash.0),
+                "Proof of work must be {} of the object hash",
+                work
+            );
+            assert!(
+                pow_public_values.output == inp.object.work,
+                "Proof of work output must match object work"
+            );
+        }
+        None => assert!(
+            inp.object.work == [0u8; 32],
+            "Proof of work output must match object work"
+        ),
+    }
+
+    let inputs = read_verified_objects(object.inputs.len(), &inp.program);
+    match_inputs(
+        &recipe.inputs,
+        object.quantity as u64,
+        &object.inputs,
+        &inputs,
+    );
+
+    // This only proves that the catalysts were crafted. Whether they are still live and owned by
+    // the crafter is checked when the object is committed.
+    let catalysts = read_verified_objects(object.catalysts.len(), &inp.program);
+    match_catalysts(&recipe.catalysts, &object.catalysts, &catalysts);
+    catalysts
+}
+
+/// Checks that the object is a stack of the same blueprint as its inputs. Whether the quantities
+/// of the stacks split or merged from them add up is checked by the commit program, which is the
+/// only one that can create restacked objects.
+fn restack(inp: &RecipeIn) {
+    let object = &inp.object.object;
+    assert!(!object.inputs.is_empty(), "Must restack some objects");
+    assert!(object.catalysts.is_empty(), "Must have no catalysts");
+    assert!(object.durability.is_none(), "Tools can't be stacked");
+    assert!(
+        inp.object.work == [0u8; 32],
+        "Proof of work output must match object work"
+    );
+
+    let inputs = read_verified_objects(object.inputs.len(), &inp.program);
+    let total: u64 = inputs.iter().map(|input| input.quantity as u64).sum();
+    match_inputs(&[object.blueprint], total, &object.inputs, &inputs);
+}
+
+/// Checks that the object is the successor of the tool it takes as input, with less durability
+/// left, and returns the tool's tier. Whether that is the durability the tool's uses leave is
+/// checked by the commit program, which is the only one that can create worn objects.
+fn wear(inp: &RecipeIn) -> Rarity {
+    let object = &inp.object.object;
+    assert!(object.inputs.len() == 1, "Must wear one tool");
+    assert!(object.catalysts.is_empty(), "Must have no catalysts");
+    assert!(
+        inp.object.work == [0u8; 32],
+        "Proof of work output must match object work"
+    );
+
+    let tool = read_verified_objects(1, &inp.program);
+    match_inputs(&[object.blueprint], 1, &object.inputs, &tool);
+    match (tool[0].durability, object.durability) {
+        (Some(before), Some(after)) => assert!(
+            0 < after && after < before,
+            "Durability must go down from {} and stay positive",
+            before
+        ),
+        _ => panic!("Only tools can be worn"),
+    }
+    tool[0].rarity
+}
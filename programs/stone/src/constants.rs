@@ -1,2 +0,0 @@
-pub const STONE_BLUEPRINT: &str = "stone";
-pub const STONE_MINING_MAX: u64 = 0x0020_0000_0000_0000;
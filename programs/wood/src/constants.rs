@@ -1,2 +0,0 @@
-pub const WOOD_BLUEPRINT: &str = "wood";
-pub const WOOD_MINING_MAX: u64 = 0x0020_0000_0000_0000;
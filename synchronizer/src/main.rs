@@ -4,7 +4,7 @@ use sp1_sdk::utils;
 use synchronizer::clients::beacon::types::BlockId;
 
 use anyhow::Result;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 mod node;
 use node::Node;
@@ -65,8 +65,11 @@ async fn main() -> Result<()> {
             }
         };
 
-        node.process_beacon_block_header(&beacon_block_header)
-            .await?;
+        if let Err(e) = node.process_beacon_block_header(&beacon_block_header).await {
+            warn!("failed to process slot {}, retrying: {:?}", slot, e);
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            continue;
+        }
         // TODO: read from env
         let request_rate = 15;
 
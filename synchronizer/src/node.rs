@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
     fs::{create_dir_all, read_dir, rename, File},
     io,
     io::{Read, Write},
@@ -9,8 +9,8 @@ use std::{
     time::Duration,
 };
 
-use commit_program::CommitOut;
-use common::ObjectHash;
+use commit_program::{Allowlist, CommitOut};
+use common::{ObjectHash, Rarity, StateTree};
 use sp1_sdk::{include_elf, EnvProver, HashableKey, ProverClient};
 use synchronizer::{
     bytes_from_simple_blob,
@@ -21,7 +21,7 @@ use synchronizer::{
     },
 };
 
-use ::utils::load_proof_from_json_file;
+use ::utils::{load_proof_from_json_file, save_state, vkeys::vk_digest_hex};
 use alloy::{
     consensus::Transaction,
     eips::{self as alloy_eips, eip4844::kzg_to_versioned_hash},
@@ -32,27 +32,47 @@ use alloy::{
 };
 use alloy_network::Ethereum;
 use alloy_provider::{Provider, RootProvider};
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use backoff::ExponentialBackoffBuilder;
 use chrono::{DateTime, Utc};
-use tracing::{debug, info, trace};
+use tracing::{debug, info, trace, warn};
 
 #[derive(Debug)]
 pub struct State {
-    created_objects: HashSet<ObjectHash>,
-    consumed_objects: HashSet<ObjectHash>,
+    /// Created objects, their current owners and whether they were consumed.
+    objects: StateTree,
+    /// Last nonce committed by each committer.
+    nonces: HashMap<Address, u64>,
+    /// Live public objects above the common tier, by tier.
+    tiers: BTreeMap<Rarity, BTreeSet<ObjectHash>>,
 }
 
 pub struct Node {
     spclient: EnvProver,
     commit_vk: sp1_sdk::SP1VerifyingKey,
+    aggregate_vk: sp1_sdk::SP1VerifyingKey,
+    /// Roots of the allowlists of object programs and recipe tables that commitments may use.
+    allowlist_roots: Vec<[u8; 32]>,
+    /// Number of slots after which an object's anchor is too old for the object to be committed.
+    anchor_max_age: u32,
     pub beacon_cli: BeaconClient,
     pub rpc_cli: RootProvider,
     // Mutable state
     state: RwLock<State>,
 }
 
+/// Lookups of an anchor block before the beacon node is deemed unavailable.
+const ANCHOR_LOOKUP_ATTEMPTS: u32 = 3;
+
+/// The beacon node couldn't tell whether a commitment is valid, so its slot must be processed
+/// again later rather than the commitment rejected.
+#[derive(Debug, thiserror::Error)]
+#[error("beacon node unavailable: {0}")]
+struct BeaconUnavailable(String);
+
 const COMMIT_ELF: &[u8] = include_elf!("commit-program");
+const AGGREGATE_ELF: &[u8] = include_elf!("aggregate-program");
+const RECIPE_ELF: &[u8] = include_elf!("recipe-program");
 
 // This node code is adapted from https://github.com/0xPARC/digital-objects-e2e-poc/blob/main/synchronizer/src/main.rs
 impl Node {
@@ -62,6 +82,44 @@ impl Node {
         println!("Setting up proving/verifying keys...");
         let (_commit_pk, commit_vk) = spclient.setup(COMMIT_ELF);
         println!("commit program vk {}", hex::encode(commit_vk.hash_bytes()));
+        let (_aggregate_pk, aggregate_vk) = spclient.setup(AGGREGATE_ELF);
+        println!(
+            "aggregate program vk {}",
+            hex::encode(aggregate_vk.hash_bytes())
+        );
+        // Allowlists are configured as comma separated hex roots, and default to the current
+        // recipe program and recipe table.
+        let allowlist_roots = match dotenvy::var("ALLOWLIST_ROOTS") {
+            Ok(roots) => roots
+                .split(',')
+                .map(|root| {
+                    let mut bytes = [0u8; 32];
+                    hex::decode_to_slice(root.trim().trim_start_matches("0x"), &mut bytes)
+                        .with_context(|| format!("invalid allowlist root {}", root))?;
+                    Ok(bytes)
+                })
+                .collect::<Result<Vec<_>>>()?,
+            Err(_) => {
+                let (_recipe_pk, recipe_vk) = spclient.setup(RECIPE_ELF);
+                let allowlist = Allowlist::current(recipe_vk.hash_u32());
+                for entry in allowlist.entries() {
+                    println!(
+                        "allowing program vk {} with recipe table {}",
+                        vk_digest_hex(&entry.program),
+                        hex::encode(entry.recipe_table)
+                    );
+                }
+                vec![allowlist.root()]
+            }
+        };
+        for root in &allowlist_roots {
+            println!("accepting allowlist root {}", hex::encode(root));
+        }
+        // Defaults to a day of slots.
+        let anchor_max_age: u32 = match dotenvy::var("ANCHOR_MAX_AGE") {
+            Ok(age) => age.parse().context("invalid anchor max age")?,
+            Err(_) => 7200,
+        };
 
         let http_cli = reqwest::Client::builder()
             .timeout(Duration::from_secs(8))
@@ -78,12 +136,16 @@ impl Node {
         let rpc_cli = RootProvider::<Ethereum>::new_http(rpc_url.parse()?);
 
         let state = State {
-            created_objects: HashSet::new(),
-            consumed_objects: HashSet::new(),
+            objects: StateTree::default(),
+            nonces: HashMap::new(),
+            tiers: BTreeMap::new(),
         };
         Ok(Self {
             spclient,
             commit_vk,
+            aggregate_vk,
+            allowlist_roots,
+            anchor_max_age,
             beacon_cli,
             rpc_cli,
             state: RwLock::new(state),
@@ -224,9 +286,9 @@ impl Node {
                 .unwrap_or_default(),
         );
         info!(
-            "current state: created_objects={:?}, consumed_objects={:?}, ",
-            self.state.read().expect("lock").created_objects,
-            self.state.read().expect("lock").consumed_objects,
+            "current state: root={}, objects={:?}",
+            hex::encode(self.state.read().expect("lock").objects.root()),
+            self.state.read().expect("lock").objects,
         );
 
         let has_kzg_blob_commitments = match beacon_block.blob_kzg_commitments {
@@ -294,10 +356,13 @@ impl Node {
             trace!(?hash, ?from, ?to);
 
             for blob in tx_blobs.iter() {
-                match self.process_do_blob(blob).await {
+                match self.process_do_blob(from, blob, slot).await {
                     Ok(_) => {
                         info!("Valid do_blob at slot {}, blob_index {}!", slot, blob.index);
                     }
+                    // The slot is processed again, and the blobs already applied are then
+                    // rejected as replays.
+                    Err(e) if e.is::<BeaconUnavailable>() => return Err(e),
                     Err(e) => {
                         info!("Invalid do_blob: {:?}", e);
                         continue;
@@ -310,8 +375,43 @@ impl Node {
 }
 
 impl Node {
+    /// Header of the beacon block with root `anchor`, or `None` if there is none. Failed lookups
+    /// are retried a few times, and then fail with [`BeaconUnavailable`] rather than rejecting
+    /// the commitment, which would fork the state from other synchronizers.
+    async fn anchor_header(
+        &self,
+        anchor: &[u8; 32],
+    ) -> Result<Option<BlockHeader>, BeaconUnavailable> {
+        let mut attempt = 1;
+        loop {
+            match self
+                .beacon_cli
+                .get_block_header(BlockId::Hash(B256::from(*anchor)))
+                .await
+            {
+                Ok(header) => return Ok(header),
+                Err(e) if attempt < ANCHOR_LOOKUP_ATTEMPTS => {
+                    warn!(
+                        "failed to get anchor block {}, retrying: {:?}",
+                        hex::encode(anchor),
+                        e
+                    );
+                    attempt += 1;
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                Err(e) => {
+                    return Err(BeaconUnavailable(format!(
+                        "failed to get anchor block {}: {}",
+                        hex::encode(anchor),
+                        e
+                    )))
+                }
+            }
+        }
+    }
+
     // This is the main function that processes the digital object blob and updates the state accordingly.
-    async fn process_do_blob(&self, blob: &Blob) -> Result<()> {
+    async fn process_do_blob(&self, from: Address, blob: &Blob, slot: u32) -> Result<()> {
         let bytes =
             bytes_from_simple_blob(blob.blob.inner()).context("Invalid byte encoding in blob")?;
         // let payload = Payload::from_bytes(&bytes, &self.common_circuit_data)?;
@@ -322,41 +422,136 @@ impl Node {
         let commit_proof =
             load_proof_from_json_file(&format!("commitments/{}.json", commit_proof_hash))
                 .expect("Expect commitment file to exist");
-        self.spclient
+        // Aggregated batches have the same public values as a single one.
+        let aggregate = self
+            .spclient
             .verify(&commit_proof, &self.commit_vk)
-            .expect("commit verify failed");
+            .is_err();
+        if aggregate {
+            self.spclient
+                .verify(&commit_proof, &self.aggregate_vk)
+                .context("commit verify failed")?;
+        }
         let commit_out: CommitOut = commit_proof.public_values.clone().read();
+        // A batch bound to an aggregate is only valid as part of it, with the aggregator's nonce.
+        if commit_out.aggregated && !aggregate {
+            bail!("batch bound to an aggregate is committed on its own");
+        }
+        if !self.allowlist_roots.contains(&commit_out.allowlist) {
+            bail!(
+                "commitment uses allowlist {}, which is not accepted",
+                hex::encode(commit_out.allowlist)
+            );
+        }
+        // Objects mined after an old block could have been mined long in advance. Private objects
+        // don't publish their anchors, and are exempt from this check.
+        for anchor in &commit_out.anchors {
+            let header = self
+                .anchor_header(anchor)
+                .await?
+                .with_context(|| format!("unknown anchor block {}", hex::encode(anchor)))?;
+            if header.slot > slot || slot - header.slot > self.anchor_max_age {
+                bail!(
+                    "anchor block {} at slot {} is not within {} slots of slot {}",
+                    hex::encode(anchor),
+                    header.slot,
+                    self.anchor_max_age,
+                    slot
+                );
+            }
+        }
         let mut state = self.state.write().expect("lock");
 
-        // Check that output is unique
-        for item in &commit_out.created {
-            if state.created_objects.contains(item) {
-                bail!("item {} exists in created_objects", item);
+        // Check that the batch is committed by its committer, and not replayed
+        let committer = Address::from(commit_out.committer);
+        if committer != from {
+            bail!("commitment by {} sent by {}", committer, from);
+        }
+        if let Some(last_nonce) = state.nonces.get(&committer) {
+            if commit_out.nonce <= *last_nonce {
+                bail!(
+                    "nonce {} of {} is not above its last nonce {}",
+                    commit_out.nonce,
+                    committer,
+                    last_nonce
+                );
             }
         }
 
-        // Check that inputs are unique
-        for item in &commit_out.consumed {
-            if !state.created_objects.contains(item) {
-                bail!("item {} doesn't exist in created_objects", item);
-            }
+        // The commit program proved the transition from `prev_root` to `new_root`, which is only
+        // valid if `prev_root` is the current state
+        let root = state.objects.root();
+        if commit_out.prev_root != root {
+            bail!(
+                "commitment applies to root {}, not the current root {}",
+                hex::encode(commit_out.prev_root),
+                hex::encode(root)
+            );
+        }
 
-            if state.consumed_objects.contains(item) {
-                bail!("item {} exists in consumed_objects", item);
+        // Update state, which the commitment only changes if it ends up at its `new_root`
+        let mut objects = state.objects.clone();
+        for update in commit_out.state_updates() {
+            ensure!(
+                objects.get(&update.key) == update.old,
+                "object {} is not {:?}",
+                update.key,
+                update.old
+            );
+            objects.apply(&[update]);
+        }
+        ensure!(
+            objects.root() == commit_out.new_root,
+            "state root mismatch after a valid commitment"
+        );
+        // The state is only changed in memory once it is saved, so that a failed save leaves the
+        // tree, the nonces and the tiers as they were.
+        save_state(&objects).map_err(|e| anyhow!("failed to save state: {}", e))?;
+        state.objects = objects;
+        state.nonces.insert(committer, commit_out.nonce);
+        for object in &commit_out.rare {
+            state
+                .tiers
+                .entry(object.rarity)
+                .or_default()
+                .insert(object.hash);
+        }
+        let gone = commit_out
+            .consumed
+            .iter()
+            .chain(commit_out.burned.iter())
+            .map(|object| object.hash)
+            .chain(commit_out.worn.iter().map(|tool| tool.hash));
+        for hash in gone {
+            for objects in state.tiers.values_mut() {
+                objects.remove(&hash);
             }
         }
-
-        // Register objects
-        for item in &commit_out.created {
-            state.created_objects.insert(item.clone());
+        for (rarity, objects) in &state.tiers {
+            info!("{} {} objects", objects.len(), rarity);
         }
-        for item in &commit_out.consumed {
-            state.consumed_objects.insert(item.clone());
+        for swap in &commit_out.swaps {
+            for leg in &swap.legs {
+                info!(
+                    "object {} swapped from {} to {}",
+                    leg.hash, leg.from, leg.to
+                );
+            }
+        }
+        for object in &commit_out.burned {
+            info!("object {} burned by {}", object.hash, object.owner);
+        }
+        for tool in &commit_out.worn {
+            match tool.successor {
+                Some(successor) => info!("tool {} worn into {}", tool.hash, successor),
+                None => info!("tool {} wore out", tool.hash),
+            }
         }
 
         info!(
-            "state update: created_objects={:?}, consumed_objects={:?}, ",
-            state.created_objects, state.consumed_objects,
+            "state update: root={}, objects={:?}",
+            hex::encode(commit_out.new_root),
+            state.objects,
         );
         Ok(())
     }
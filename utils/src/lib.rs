@@ -1,13 +1,17 @@
-use common::{Object, ObjectHash};
+use common::{Object, ObjectHash, ObjectOutput, StateTree};
 use serde::{Deserialize, Serialize};
-use sp1_sdk::SP1ProofWithPublicValues;
+use sp1_sdk::{SP1Proof, SP1ProofWithPublicValues, SP1Stdin};
 use std::{fs::File, io::Write, path::Path};
 
+pub mod owner;
+pub mod vkeys;
+
 #[derive(Serialize, Deserialize)]
 pub struct ObjectJson {
     pub object: Object,
     pub hash: ObjectHash,
-    pub work: String,
+    #[serde(with = "hex::serde")]
+    pub work: [u8; 32],
     pub proof: SP1ProofWithPublicValues,
     pub program_vk: sp1_sdk::SP1VerifyingKey,
 }
@@ -32,6 +36,21 @@ impl ObjectJson {
         let object_json: ObjectJson = serde_json::from_reader(file)?;
         Ok(object_json)
     }
+
+    /// Public values of the object's proof.
+    pub fn output(&self) -> ObjectOutput {
+        self.proof.public_values.clone().read()
+    }
+
+    /// Writes the object for a program to verify: its output and its proof.
+    pub fn write_to(&self, stdin: &mut SP1Stdin) {
+        stdin.write(&self.output());
+
+        let SP1Proof::Compressed(compressed) = self.proof.proof.clone() else {
+            panic!("expected compressed proof")
+        };
+        stdin.write_proof(*compressed, self.program_vk.vk.clone());
+    }
 }
 
 pub fn save_proof_as_json(
@@ -51,3 +70,24 @@ pub fn load_proof_from_json_file(
     let proof: SP1ProofWithPublicValues = serde_json::from_reader(file)?;
     Ok(proof)
 }
+
+/// Where the synchronizer publishes the current state tree, from which committers prove their
+/// state updates.
+pub const STATE_FILE: &str = "state.json";
+
+pub fn save_state(state: &StateTree) -> Result<(), Box<dyn std::error::Error>> {
+    let json = serde_json::to_string(state)?;
+    let tmp = format!("{}.tmp", STATE_FILE);
+    File::create(&tmp)?.write_all(json.as_bytes())?;
+    std::fs::rename(tmp, STATE_FILE)?;
+    Ok(())
+}
+
+/// Loads the state published by the synchronizer, which is empty until the first commitment.
+pub fn load_state() -> Result<StateTree, Box<dyn std::error::Error>> {
+    match File::open(STATE_FILE) {
+        Ok(file) => Ok(serde_json::from_reader(file)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(StateTree::default()),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,28 @@
+use common::{OwnerKey, OwnerSignature};
+use k256::ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey};
+use sha2::{Digest, Sha256};
+
+/// Loads the object owner's secp256k1 key from the `OWNER_PRIVATE_KEY` environment variable.
+pub fn load_owner_key() -> Result<SigningKey, Box<dyn std::error::Error>> {
+    let key_hex = dotenvy::var("OWNER_PRIVATE_KEY")?;
+    let bytes = hex::decode(key_hex.trim_start_matches("0x"))?;
+    Ok(SigningKey::from_slice(&bytes)?)
+}
+
+pub fn owner_key(key: &SigningKey) -> OwnerKey {
+    OwnerKey::from(key.verifying_key())
+}
+
+pub fn sign_digest(key: &SigningKey, digest: &[u8; 32]) -> OwnerSignature {
+    let signature: Signature = key.sign_prehash(digest).expect("failed to sign digest");
+    OwnerSignature::from(&signature)
+}
+
+/// Secret behind the owner's private objects, derived from the owner key so that it needs no
+/// separate backup.
+pub fn owner_secret(key: &SigningKey) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"digital-objects/owner-secret");
+    hasher.update(key.to_bytes());
+    hasher.finalize().into()
+}
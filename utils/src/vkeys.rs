@@ -0,0 +1,66 @@
+#[cfg(feature = "build")]
+use std::{fmt::Write, path::PathBuf};
+
+#[cfg(feature = "build")]
+use sp1_build::{build_program_with_args, BuildArgs};
+#[cfg(feature = "build")]
+use sp1_sdk::ProverClient;
+use sp1_sdk::{HashableKey, SP1VerifyingKey};
+
+/// Builds each `(name, path)` program and writes its vkey digest to `$OUT_DIR/vkeys.rs` as a
+/// `{name}_VKEY_DIGEST: [u32; 8]` constant.
+///
+/// Meant to be called from the build script of a program that verifies proofs of the given
+/// programs, which then includes the generated file. Requires the `build` feature.
+#[cfg(feature = "build")]
+pub fn generate_vkey_digests(programs: &[(&str, &str)]) {
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    let client = ProverClient::builder().cpu().build();
+
+    let mut src = String::new();
+    for (name, path) in programs {
+        let elf_name = format!("{}-elf", name.to_lowercase());
+        build_program_with_args(
+            path,
+            BuildArgs {
+                output_directory: Some(out_dir.display().to_string()),
+                elf_name: Some(elf_name.clone()),
+                ..Default::default()
+            },
+        );
+        let elf = std::fs::read(out_dir.join(&elf_name)).expect("failed to read program elf");
+        let (_, vk) = client.setup(&elf);
+        writeln!(
+            src,
+            "pub const {}_VKEY_DIGEST: [u32; 8] = {:?};",
+            name,
+            vk.hash_u32()
+        )
+        .unwrap();
+    }
+
+    std::fs::write(out_dir.join("vkeys.rs"), src).expect("failed to write vkeys.rs");
+}
+
+/// Hex encoding of a vkey digest, matching `hex::encode(vk.hash_bytes())`.
+pub fn vk_digest_hex(digest: &[u32; 8]) -> String {
+    digest.iter().map(|word| format!("{:08x}", word)).collect()
+}
+
+/// Checks that the vk produced by `client.setup` is the one a dependent program was built with.
+pub fn check_vk_digest(
+    name: &str,
+    vk: &SP1VerifyingKey,
+    expected: &[u32; 8],
+) -> Result<(), Box<dyn std::error::Error>> {
+    if vk.hash_u32() != *expected {
+        return Err(format!(
+            "{} program vk {} does not match the baked-in digest {}",
+            name,
+            hex::encode(vk.hash_bytes()),
+            vk_digest_hex(expected)
+        )
+        .into());
+    }
+    Ok(())
+}